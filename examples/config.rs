@@ -0,0 +1,51 @@
+use std::time::{Duration, Instant};
+
+use metrics::{describe_gauge, gauge, Unit};
+use metrics_dashboard::{build_dashboard_route, DashboardOptions, HttpMetricMiddleware};
+use poem::{
+    get, handler, listener::TcpListener, middleware::Tracing, web::Path, EndpointExt, Route, Server,
+};
+
+#[handler]
+fn hello(Path(name): Path<String>) -> String {
+    format!("hello: {name}")
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    if std::env::var_os("RUST_LOG").is_none() {
+        std::env::set_var("RUST_LOG", "poem=debug");
+    }
+    tracing_subscriber::fmt::init();
+
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "examples/dashboard.yaml".to_string());
+    let dashboard_options = DashboardOptions::from_file(&path)?;
+
+    let app = Route::new()
+        .at("/hello/:name", get(hello))
+        .nest("/dashboard/", build_dashboard_route(dashboard_options))
+        .with(
+            HttpMetricMiddleware::new()
+                .with_method_label(true)
+                .with_route_label(true)
+                .exclude_path("/dashboard/*"),
+        )
+        .with(Tracing);
+
+    tokio::spawn(async move {
+        describe_gauge!("demo_live_time", Unit::Seconds, "Demo live time");
+        let start = Instant::now();
+        loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            gauge!("demo_live_time").set(start.elapsed());
+        }
+    });
+
+    Server::new(TcpListener::bind("0.0.0.0:3000"))
+        .name("hello-world")
+        .run(app)
+        .await?;
+    Ok(())
+}
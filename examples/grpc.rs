@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use metrics_dashboard::{build_dashboard_route, grpc::GrpcMetricLayer, DashboardOptions};
+use poem::{listener::TcpListener, middleware::Tracing, EndpointExt, Route, Server};
+use tonic::transport::{Channel, Server as GrpcServer};
+use tonic_health::pb::{health_client::HealthClient, HealthCheckRequest};
+
+#[tokio::main]
+async fn main() -> Result<(), std::io::Error> {
+    if std::env::var_os("RUST_LOG").is_none() {
+        std::env::set_var("RUST_LOG", "poem=debug");
+    }
+    tracing_subscriber::fmt::init();
+
+    let dashboard_options = DashboardOptions {
+        custom_charts: vec![],
+        include_default: true,
+        ..Default::default()
+    };
+
+    let app = Route::new()
+        .nest("/dashboard/", build_dashboard_route(dashboard_options))
+        .with(Tracing);
+
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter
+        .set_service_status("demo", tonic_health::ServingStatus::Serving)
+        .await;
+
+    tokio::spawn(async move {
+        GrpcServer::builder()
+            .layer(GrpcMetricLayer::new())
+            .add_service(health_service)
+            .serve("127.0.0.1:50051".parse().expect("Should parse address"))
+            .await
+            .expect("Should run grpc server");
+    });
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let channel = Channel::from_static("http://127.0.0.1:50051")
+            .connect()
+            .await
+            .expect("Should connect to grpc server");
+        let mut client = HealthClient::new(channel);
+        loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let _ = client
+                .check(HealthCheckRequest {
+                    service: "demo".to_string(),
+                })
+                .await;
+            // Unknown service, which is answered with NOT_FOUND
+            let _ = client
+                .check(HealthCheckRequest {
+                    service: "unknown".to_string(),
+                })
+                .await;
+        }
+    });
+
+    Server::new(TcpListener::bind("0.0.0.0:3000"))
+        .name("grpc-demo")
+        .run(app)
+        .await
+}
@@ -1,7 +1,10 @@
 use std::time::{Duration, Instant};
 
 use metrics::{counter, describe_counter, describe_gauge, gauge, Unit};
-use metrics_dashboard::{build_dashboard_route, ChartType, DashboardOptions, HttpMetricMiddleware};
+use metrics_dashboard::{
+    build_dashboard_route_with_recorder, Axis, ChartType, Dashboard, DashboardOptions,
+    DefaultGrouping, HttpMetricMiddleware, LineStyle, Panel, Section, SeriesStyle,
+};
 use poem::{
     get, handler, listener::TcpListener, middleware::Tracing, web::Path, EndpointExt, Route, Server,
 };
@@ -19,32 +22,112 @@ async fn main() -> Result<(), std::io::Error> {
     tracing_subscriber::fmt::init();
 
     let dashboard_options = DashboardOptions {
-        custom_charts: vec![
-            ChartType::Bar {
-                metrics: vec![
-                    "demo_metric2".to_string(),
-                    "demo_metric3".to_string(),
-                    "demo_metric4".to_string(),
-                ],
-                desc: "Demo metric bar".to_string(),
-                unit: Unit::Count.as_canonical_label().to_string(),
-            },
-            ChartType::Line {
-                metrics: vec![
-                    "http_requests_total".to_string(),
-                    "http_requests_error".to_string(),
-                ],
-                desc: "Http requests".to_string(),
-                unit: Unit::Count.as_canonical_label().to_string(),
-            },
-        ],
         include_default: true,
+        default_grouping: DefaultGrouping::Prefix,
+        sections: vec![Section {
+            title: "Http".to_string(),
+            charts: vec![
+                ChartType::line(
+                    vec![
+                        "http_requests_total".to_string(),
+                        "http_requests_error".to_string(),
+                    ],
+                    "Http requests",
+                    Unit::Count.as_canonical_label(),
+                )
+                .with_style(
+                    "http_requests_error",
+                    SeriesStyle {
+                        alias: Some("errors".to_string()),
+                        color: Some("#f55555".to_string()),
+                        line: LineStyle::Dashed,
+                        ..Default::default()
+                    },
+                )
+                .into(),
+                ChartType::StackedArea {
+                    metrics: vec![
+                        "http_requests_total{status_class=\"2xx\"}".to_string(),
+                        "http_requests_total{status_class=\"4xx\"}".to_string(),
+                        "http_requests_total{status_class=\"5xx\"}".to_string(),
+                    ],
+                    desc: "Http requests by status".to_string(),
+                    unit: Unit::Count.as_canonical_label().to_string(),
+                    stacked: true,
+                    percent: true,
+                }
+                .into(),
+                ChartType::Pie {
+                    // One slice per method, including the ones seen later
+                    metrics: vec!["http_requests_total{method=~\".+\"}".to_string()],
+                    desc: "Http requests by method".to_string(),
+                    unit: Unit::Count.as_canonical_label().to_string(),
+                    donut: true,
+                }
+                .into(),
+                Panel::new(ChartType::Table {
+                    metric: "http_requests_total".to_string(),
+                    desc: "Http requests by route".to_string(),
+                    unit: "".to_string(),
+                    sort: "rate".to_string(),
+                    descending: true,
+                    limit: 10,
+                })
+                .with_width(8),
+                Panel::new(ChartType::Text {
+                    content: "**Runbook**: restart the service when the error rate stays high.\n\n\
+                              Current error rate: {{rate(http_requests_error)}}"
+                        .to_string(),
+                    desc: "Http runbook".to_string(),
+                })
+                .with_width(4),
+            ],
+            include: vec!["http".to_string()],
+            ..Default::default()
+        }],
+        editable: true,
+        dashboards: vec![Dashboard {
+            name: "Demo".to_string(),
+            custom_charts: vec![
+                ChartType::bar(
+                    vec![
+                        "demo_metric2".to_string(),
+                        "demo_metric3".to_string(),
+                        "demo_metric4".to_string(),
+                    ],
+                    "Demo metric bar",
+                    Unit::Count.as_canonical_label(),
+                )
+                .with_axis(Axis {
+                    log: true,
+                    ..Default::default()
+                }),
+                ChartType::Scatter {
+                    metrics: ["demo_live_time".to_string(), "demo_metric2".to_string()],
+                    desc: "Demo metric2 by live time".to_string(),
+                    x_unit: Unit::Seconds.as_canonical_label().to_string(),
+                    y_unit: Unit::Count.as_canonical_label().to_string(),
+                    trend: true,
+                },
+            ],
+            ..Default::default()
+        }],
+        ..Default::default()
     };
 
+    let (dashboard, dashboard_route) = build_dashboard_route_with_recorder(dashboard_options);
+    dashboard.annotate("Server started", ["deploy"]);
+
     let app = Route::new()
         .at("/hello/:name", get(hello))
-        .nest("/dashboard/", build_dashboard_route(dashboard_options))
-        .with(HttpMetricMiddleware)
+        .nest("/dashboard/", dashboard_route)
+        .with(
+            HttpMetricMiddleware::new()
+                .with_method_label(true)
+                .with_route_label(true)
+                .with_status_class_label(true)
+                .exclude_path("/dashboard/*"),
+        )
         .with(Tracing);
 
     tokio::spawn(async move {
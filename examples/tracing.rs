@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use metrics_dashboard::{
+    build_dashboard_route, log_metrics::LogMetricsLayer, tracing_layer::MetricsLayer,
+    DashboardOptions,
+};
+use poem::{listener::TcpListener, middleware::Tracing, EndpointExt, Route, Server};
+use tracing::Instrument;
+use tracing_subscriber::prelude::*;
+
+#[tokio::main]
+async fn main() -> Result<(), std::io::Error> {
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer())
+        .with(MetricsLayer::new().with_field_label("kind", "kind"))
+        .with(LogMetricsLayer::new())
+        .init();
+
+    let dashboard_options = DashboardOptions {
+        custom_charts: vec![],
+        include_default: true,
+        ..Default::default()
+    };
+
+    let app = Route::new()
+        .nest("/dashboard/", build_dashboard_route(dashboard_options))
+        .with(Tracing);
+
+    tokio::spawn(async move {
+        let mut round: u64 = 0;
+        loop {
+            round += 1;
+            let kind = if round.is_multiple_of(2) {
+                "even"
+            } else {
+                "odd"
+            };
+            async {
+                tokio::time::sleep(Duration::from_millis(100 * (round % 5))).await;
+                if round.is_multiple_of(10) {
+                    tracing::warn!("slow round");
+                }
+            }
+            .instrument(tracing::info_span!("demo_job", kind))
+            .await;
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+
+    Server::new(TcpListener::bind("0.0.0.0:3000"))
+        .name("tracing-demo")
+        .run(app)
+        .await
+}
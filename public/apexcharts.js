@@ -139,3 +139,238 @@ window.ApexOptionsLine = {
     show: true,
   },
 };
+
+window.ApexOptionsHistogram = {
+  chart: {
+    height: 250,
+    type: "bar",
+    animations: {
+      enabled: false,
+    },
+    toolbar: {
+      show: false,
+    },
+    zoom: {
+      enabled: false,
+    },
+  },
+  plotOptions: {
+    bar: {
+      columnWidth: "95%",
+    },
+  },
+  dataLabels: {
+    enabled: false,
+  },
+  series: [],
+  xaxis: {
+    type: "category",
+    categories: [],
+  },
+  tooltip: {
+    theme: "dark",
+    x: {
+      formatter: undefined,
+    },
+  },
+  legend: {
+    show: true,
+  },
+};
+
+window.ApexOptionsHeatmap = {
+  chart: {
+    height: 250,
+    type: "heatmap",
+    animations: {
+      enabled: false,
+    },
+    toolbar: {
+      show: false,
+    },
+    zoom: {
+      enabled: false,
+    },
+  },
+  dataLabels: {
+    enabled: false,
+  },
+  colors: ["#17ead9"],
+  series: [],
+  xaxis: {
+    type: "category",
+    labels: {
+      show: false,
+    },
+  },
+  tooltip: {
+    theme: "dark",
+    x: {
+      formatter: undefined,
+    },
+  },
+  legend: {
+    show: false,
+  },
+};
+
+window.ApexOptionsScatter = {
+  chart: {
+    height: 250,
+    type: "line",
+    animations: {
+      enabled: false,
+    },
+    toolbar: {
+      show: false,
+    },
+    zoom: {
+      enabled: false,
+    },
+  },
+  dataLabels: {
+    enabled: false,
+  },
+  stroke: {
+    width: [0, 2],
+  },
+  markers: {
+    size: [4, 0],
+  },
+  fill: {
+    type: "solid",
+  },
+  series: [],
+  xaxis: {
+    type: "numeric",
+    tickAmount: 5,
+  },
+  tooltip: {
+    theme: "dark",
+    x: {
+      formatter: undefined,
+    },
+  },
+  legend: {
+    show: false,
+  },
+};
+
+window.ApexOptionsSparkline = {
+  chart: {
+    height: 60,
+    type: "area",
+    sparkline: {
+      enabled: true,
+    },
+    animations: {
+      enabled: false,
+    },
+  },
+  stroke: {
+    width: 2,
+  },
+  series: [],
+  xaxis: {
+    type: "datetime",
+  },
+  tooltip: {
+    theme: "dark",
+    x: {
+      show: false,
+    },
+  },
+};
+
+window.ApexOptionsGauge = {
+  chart: {
+    height: 250,
+    type: "radialBar",
+    animations: {
+      enabled: true,
+    },
+  },
+  plotOptions: {
+    radialBar: {
+      startAngle: -135,
+      endAngle: 135,
+      track: {
+        background: "#40475D",
+      },
+      dataLabels: {
+        name: {
+          show: false,
+        },
+        value: {
+          fontSize: "24px",
+          color: "#fff",
+        },
+      },
+    },
+  },
+  fill: {
+    type: "solid",
+  },
+  series: [],
+};
+
+window.ApexOptionsStackedArea = {
+  chart: {
+    height: 250,
+    type: "area",
+    stacked: true,
+    animations: {
+      enabled: true,
+    },
+    toolbar: {
+      show: false,
+    },
+    zoom: {
+      enabled: false,
+    },
+  },
+  dataLabels: {
+    enabled: false,
+  },
+  stroke: {
+    width: 1,
+  },
+  fill: {
+    type: "solid",
+    opacity: 0.6,
+  },
+  series: [],
+  xaxis: {
+    type: "datetime",
+    range: 300000,
+  },
+  legend: {
+    show: true,
+  },
+};
+
+window.ApexOptionsPie = {
+  chart: {
+    height: 250,
+    type: "pie",
+    animations: {
+      enabled: true,
+    },
+  },
+  dataLabels: {
+    enabled: true,
+  },
+  fill: {
+    type: "solid",
+  },
+  stroke: {
+    colors: ["#40475D"],
+  },
+  colors: ["#FCCF31", "#17ead9", "#f02fc2", "#6078ea", "#f5a623", "#f55555"],
+  legend: {
+    show: true,
+    position: "bottom",
+  },
+  series: [],
+  labels: [],
+};
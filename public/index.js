@@ -5,12 +5,165 @@ import {
   useEffect,
   useRef,
 } from "https://esm.sh/htm/preact/standalone";
+import { marked } from "https://esm.sh/marked@12";
+import DOMPurify from "https://esm.sh/dompurify@3";
 
 const BusChannel = {};
 const CachedChannel = {};
+// Series keys matched by each selector, resolved by the server at each load
+const SeriesChannel = {};
 const Metrics = {};
 
-const LineChart = ({ idx, metrics, desc, unit }) => {
+// Series drawn for the metrics of a chart, selectors replaced by their series
+const expandSeries = (metrics) =>
+  (metrics || []).flatMap((m) => SeriesChannel[m] || [m]);
+
+const cachedValue = (key) => (CachedChannel[key] ? CachedChannel[key][1] : 0);
+
+// Events marked on the time series charts, in the order they were recorded
+const Annotations = [];
+// Age of the oldest annotations loaded with the dashboard
+const ANNOTATIONS_WINDOW_MS = 10 * 60 * 1000;
+// Sequence number of the last annotation received, null before the first load
+let LastAnnotationId = null;
+
+const annotationOptions = () => ({
+  xaxis: Annotations.map((a) => ({
+    x: a.ts,
+    borderColor: "#f5a623",
+    label: {
+      text: a.tags.length ? a.text + " (" + a.tags.join(", ") + ")" : a.text,
+      orientation: "horizontal",
+      style: { color: "#000", background: "#f5a623" },
+    },
+  })),
+});
+
+// Display of the units by label and name, loaded from the server
+const Units = {};
+const unitInfo = (unit) => unit && (Units[unit] || Units[unit.toLowerCase()]);
+
+// Name of the metric of a key like `rate(name{label="value"})`
+const metricName = (key) =>
+  (key || "")
+    .replace(/^\w+\((\s*[\d.]+\s*,)?/, "")
+    .split(/[{,)]/)[0]
+    .trim();
+
+// Decimal places of the values of a metric, from its display options
+const metricPrecision = (key) => Metrics[metricName(key)]?.precision;
+
+// Formats a value in the largest scale of its unit not greater than the value,
+// like 1536 bytes as "1.5 KiB", with the precision of the metric of the key if any
+const formatValue = (value, unit, key) => {
+  if (value === null || value === undefined) {
+    return "--";
+  }
+  const info = unitInfo(unit);
+  const precision = metricPrecision(key) ?? info?.precision ?? 2;
+  if (!info) {
+    return +value.toFixed(precision) + (unit ? " " + unit : "");
+  }
+  const base = value * info.factor;
+  // Values below the smallest scale, like 0, stay in their own unit
+  const scale =
+    info.scales.filter((s) => Math.abs(base) >= s.factor).pop() ||
+    info.scales.find((s) => s.label === info.label);
+  const scaled = +(base / scale.factor).toFixed(precision);
+  return scaled + (scale.label ? " " + scale.label : "");
+};
+
+// Formats the axis labels and tooltips of a chart in the unit of its values
+const withUnit = (opts, unit, key) => {
+  const formatter = (value) => formatValue(value, unit, key);
+  opts.yaxis = Object.assign({}, window.Apex.yaxis, {
+    labels: Object.assign({}, window.Apex.yaxis.labels, { formatter }),
+  });
+  opts.tooltip = Object.assign({}, opts.tooltip, { y: { formatter } });
+};
+
+// Series drawn for the metrics of a chart with their configured display. The style of a
+// selector applies to all its series, its alias shown before their labels.
+const styledSeries = (metrics, styles) =>
+  (metrics || []).flatMap((m) =>
+    (SeriesChannel[m] || [m]).map((key) => {
+      const own = styles?.[key];
+      const style = own || styles?.[m] || {};
+      const alias =
+        style.alias && (own || key === m)
+          ? style.alias
+          : style.alias && style.alias + key.slice(key.indexOf("{"));
+      return { key, name: alias || key, style };
+    })
+  );
+
+const DASH_ARRAYS = { Solid: 0, Dashed: 6, Dotted: 2 };
+
+// Y-axis bounds and scale of a line or bar chart
+const axisBounds = (axis) => ({
+  logarithmic: !!axis?.log,
+  min: axis?.min ?? undefined,
+  max: axis?.max ?? undefined,
+});
+
+// Options of a line chart drawing the series, the ones styled as secondary on a second
+// y-axis in its own unit
+const lineOptions = (series, data, axis, unit) => {
+  const isSecondary = (s) => s.style.secondary;
+  const yaxis = (axisSeries, unit, extra) =>
+    Object.assign({}, window.Apex.yaxis, extra, {
+      seriesName: axisSeries && axisSeries.map((s) => s.name),
+      labels: Object.assign({}, window.Apex.yaxis.labels, {
+        formatter: (value) =>
+          formatValue(value, unit, (axisSeries || series)[0]?.key),
+      }),
+    });
+  const secondary = series.filter(isSecondary);
+  const primary = series.filter((s) => !isSecondary(s));
+  return {
+    series: series.map((s) => {
+      const res = { name: s.name, data: data[s.key] };
+      if (s.style.color) {
+        res.color = s.style.color;
+      }
+      return res;
+    }),
+    stroke: {
+      width: 2,
+      dashArray: series.map((s) => DASH_ARRAYS[s.style.line] || 0),
+    },
+    yaxis: secondary.length
+      ? [
+          yaxis(primary, unit, axisBounds(axis)),
+          yaxis(secondary, axis.secondary_unit, {
+            logarithmic: !!axis.log,
+            opposite: !window.Apex.yaxis.opposite,
+          }),
+        ]
+      : yaxis(undefined, unit, axisBounds(axis)),
+    tooltip: {
+      y: {
+        formatter: (value, { seriesIndex }) =>
+          formatValue(
+            value,
+            series[seriesIndex] && isSecondary(series[seriesIndex])
+              ? axis.secondary_unit
+              : unit,
+            series[seriesIndex]?.key
+          ),
+      },
+    },
+  };
+};
+
+// Overrides the height of the chart options, when configured
+const withHeight = (opts, height) => {
+  if (height) {
+    opts.chart = Object.assign({}, opts.chart, { height });
+  }
+};
+
+const LineChart = ({ idx, metrics, desc, unit, axis, styles, height }) => {
   const elm = useRef(null);
   const [value, setValue] = useState();
   useEffect(() => {
@@ -22,44 +175,38 @@ const LineChart = ({ idx, metrics, desc, unit }) => {
     }
     const isMulti = metrics?.length > 1;
     const data = {};
-    const opts = Object.assign({}, window.ApexOptionsLine);
-
-    metrics?.map((m) => {
-      const key = m;
-      const value = CachedChannel[key] ? [CachedChannel[key]] : [];
-      data[key] = value;
+    expandSeries(metrics).map((m) => {
+      data[m] = CachedChannel[m] ? [CachedChannel[m]] : [];
     });
 
-    opts.series = metrics?.map((m) => {
-      return {
-        name: m,
-        data: data[m],
-      };
-    });
+    const opts = Object.assign(
+      {},
+      window.ApexOptionsLine,
+      lineOptions(styledSeries(metrics, styles), data, axis || {}, unit)
+    );
+    withHeight(opts, height);
 
     const chart = new ApexCharts(elm.current, opts);
     chart.render();
 
     BusChannel[idx] = (date) => {
-      metrics?.map((m) => {
-        const value = CachedChannel[m] ? CachedChannel[m][1] : 0;
-        if (!isMulti) {
-          setValue(value);
-        }
-        data[m].push([date, value]);
-        if (data[m].length > 100) {
-          data[m].shift();
+      if (!isMulti) {
+        setValue(cachedValue(metrics[0]));
+      }
+      const series = styledSeries(metrics, styles);
+      series.map(({ key }) => {
+        // Series of a selector can appear after the chart is created
+        data[key] = data[key] || [];
+        data[key].push([date, cachedValue(key)]);
+        if (data[key].length > 100) {
+          data[key].shift();
         }
       });
 
-      const options = {
-        series: metrics?.map((m) => {
-          return {
-            name: m,
-            data: data[m],
-          };
-        }),
-      };
+      const options = Object.assign(
+        lineOptions(series, data, axis || {}, unit),
+        { annotations: annotationOptions() }
+      );
       chart.updateOptions(options, false, false);
     };
 
@@ -68,19 +215,17 @@ const LineChart = ({ idx, metrics, desc, unit }) => {
     };
   }, [elm, metrics]);
 
-  return html` <div class="col-md-4">
-    <div class="box columnbox mt-4">
-      <div class="header">
-        <h3 class="title">${desc || metrics?.join(",")}</h3>
-        ${metrics?.length === 1 &&
-        html`<h2 class="subtitle">${value || "--"} ${unit ? unit : ""}</h2>`}
-      </div>
-      <div ref=${elm}></div>
+  return html`<div class="box columnbox mt-4">
+    <div class="header">
+      <h3 class="title">${desc || metrics?.join(",")}</h3>
+      ${metrics?.length === 1 &&
+      html`<h2 class="subtitle">${formatValue(value, unit, metrics[0])}</h2>`}
     </div>
+    <div ref=${elm}></div>
   </div>`;
 };
 
-const BarChart = ({ idx, metrics, desc, unit }) => {
+const BarChart = ({ idx, metrics, desc, unit, axis, styles, height }) => {
   const elm = useRef(null);
   const [value, setValue] = useState();
   useEffect(() => {
@@ -92,67 +237,662 @@ const BarChart = ({ idx, metrics, desc, unit }) => {
     }
     const isMulti = metrics?.length > 1;
     const opts = Object.assign({}, window.ApexOptionsBar);
+    withHeight(opts, height);
+    // Values are on the x axis of horizontal bars
+    const formatter = (value) => formatValue(value, unit, metrics[0]);
+    opts.xaxis = { labels: { formatter } };
+    // The bounds and scale of the values still belong to the y axis
+    opts.yaxis = Object.assign({}, window.Apex.yaxis, axisBounds(axis));
+    opts.dataLabels = Object.assign({}, opts.dataLabels, { formatter });
+    opts.tooltip = { y: { formatter } };
 
-    opts.series[0].data = metrics?.map((m) => {
-      const value = CachedChannel[m] ? CachedChannel[m][1] : 0;
-      return {
-        x: m,
-        y: value,
-      };
+    const bars = () =>
+      styledSeries(metrics, styles).map(({ key, name, style }) => {
+        const bar = { x: name, y: cachedValue(key) };
+        if (style.color) {
+          bar.fillColor = style.color;
+        }
+        return bar;
+      });
+    opts.series = [{ data: bars() }];
+
+    const chart = new ApexCharts(elm.current, opts);
+    chart.render();
+
+    BusChannel[idx] = (date) => {
+      if (!isMulti) {
+        setValue(cachedValue(metrics[0]));
+      }
+
+      chart.updateOptions({ series: [{ data: bars() }] }, false, false);
+    };
+
+    return () => {
+      delete BusChannel[idx];
+    };
+  }, [elm, metrics]);
+
+  return html`<div class="box columnbox mt-4">
+    <div class="header">
+      <h3 class="title">${desc || metrics?.join(",")}</h3>
+      ${metrics?.length === 1 &&
+      html`<h2 class="subtitle">${formatValue(value, unit, metrics[0])}</h2>`}
+    </div>
+    <div ref=${elm}></div>
+  </div>`;
+};
+
+const StackedAreaChart = ({ idx, meta, height }) => {
+  const elm = useRef(null);
+  useEffect(() => {
+    if (!elm) {
+      return;
+    }
+    const data = {};
+    expandSeries(meta.metrics).map((m) => {
+      data[m] = CachedChannel[m] ? [CachedChannel[m]] : [];
     });
+    const series = () =>
+      expandSeries(meta.metrics).map((m) => ({ name: m, data: data[m] }));
 
+    const opts = Object.assign({}, window.ApexOptionsStackedArea);
+    withHeight(opts, height);
+    if (!meta.percent) {
+      withUnit(opts, meta.unit, meta.metrics[0]);
+    }
+    opts.chart = Object.assign({}, opts.chart, {
+      stacked: meta.stacked || meta.percent,
+      stackType: meta.percent ? "100%" : "normal",
+    });
+    opts.series = series();
     const chart = new ApexCharts(elm.current, opts);
     chart.render();
 
     BusChannel[idx] = (date) => {
-      metrics?.map((m) => {
-        const value = CachedChannel[m] ? CachedChannel[m][1] : 0;
-        if (!isMulti) {
-          setValue(value);
+      expandSeries(meta.metrics).map((m) => {
+        data[m] = data[m] || [];
+        data[m].push([date, cachedValue(m)]);
+        if (data[m].length > 100) {
+          data[m].shift();
         }
       });
+      chart.updateOptions(
+        { series: series(), annotations: annotationOptions() },
+        false,
+        false
+      );
+    };
+
+    return () => {
+      delete BusChannel[idx];
+    };
+  }, [elm, meta]);
+
+  return html`<div class="box columnbox mt-4">
+    <div class="header">
+      <h3 class="title">${meta.desc || meta.metrics?.join(",")}</h3>
+      ${meta.percent && html`<h2 class="subtitle">%</h2>`}
+    </div>
+    <div ref=${elm}></div>
+  </div>`;
+};
+
+const PieChart = ({ idx, meta, height }) => {
+  const elm = useRef(null);
+  useEffect(() => {
+    if (!elm) {
+      return;
+    }
+    const values = () => expandSeries(meta.metrics).map(cachedValue);
+
+    const opts = Object.assign({}, window.ApexOptionsPie);
+    withHeight(opts, height);
+    opts.chart = Object.assign({}, opts.chart, {
+      type: meta.donut ? "donut" : "pie",
+    });
+    opts.labels = expandSeries(meta.metrics);
+    opts.series = values();
+    opts.tooltip = {
+      y: {
+        formatter: (value) => formatValue(value, meta.unit, meta.metrics[0]),
+      },
+    };
+    const chart = new ApexCharts(elm.current, opts);
+    chart.render();
+
+    BusChannel[idx] = (_date) => {
+      chart.updateOptions(
+        { labels: expandSeries(meta.metrics), series: values() },
+        false,
+        false
+      );
+    };
+
+    return () => {
+      delete BusChannel[idx];
+    };
+  }, [elm, meta]);
+
+  return html`<div class="box columnbox mt-4">
+    <div class="header">
+      <h3 class="title">${meta.desc || meta.metrics?.join(",")}</h3>
+    </div>
+    <div ref=${elm}></div>
+  </div>`;
+};
+
+const fetchHistogram = async (key) => {
+  const res = await fetch("api/histogram?key=" + encodeURIComponent(key));
+  if (!res.ok) {
+    return null;
+  }
+  return await res.json();
+};
+
+const bucketLabel = (bounds, i, unit) => {
+  if (bounds[i] === null) {
+    return "> " + formatValue(i > 0 ? bounds[i - 1] : 0, unit);
+  }
+  return "≤ " + formatValue(bounds[i], unit);
+};
+
+const HistogramChart = ({ idx, metrics, desc, unit, height }) => {
+  const elm = useRef(null);
+  useEffect(() => {
+    if (!elm) {
+      return;
+    }
+    if (!metrics) {
+      return;
+    }
+    const opts = Object.assign({}, window.ApexOptionsHistogram);
+    withHeight(opts, height);
+    opts.series = metrics.map((m) => ({ name: m, data: [] }));
+    const chart = new ApexCharts(elm.current, opts);
+    chart.render();
+
+    BusChannel[idx] = async (_date) => {
+      const values = await Promise.all(metrics.map(fetchHistogram));
+      // Buckets of all metrics, ordered by bound with the unbounded one last
+      const bounds = [
+        ...new Set(values.filter((v) => v).flatMap((v) => v.bounds)),
+      ].sort((a, b) => (a === null) - (b === null) || a - b);
+      const series = metrics.map((m, i) => {
+        const totals = bounds.map(() => 0);
+        values[i]?.samples.map((s) => {
+          s.counts.map((c, j) => {
+            totals[bounds.indexOf(values[i].bounds[j])] += c;
+          });
+        });
+        return { name: m, data: totals };
+      });
+      chart.updateOptions(
+        {
+          series,
+          xaxis: {
+            categories: bounds.map((_b, i) => bucketLabel(bounds, i, unit)),
+          },
+        },
+        false,
+        false
+      );
+    };
+
+    return () => {
+      delete BusChannel[idx];
+    };
+  }, [elm, metrics]);
+
+  return html`<div class="box columnbox mt-4">
+    <div class="header">
+      <h3 class="title">${desc || metrics?.join(",")}</h3>
+    </div>
+    <div ref=${elm}></div>
+  </div>`;
+};
+
+const HeatmapChart = ({ idx, metrics, desc, unit, height }) => {
+  const elm = useRef(null);
+  useEffect(() => {
+    if (!elm) {
+      return;
+    }
+    if (!metrics) {
+      return;
+    }
+    const opts = Object.assign({}, window.ApexOptionsHeatmap);
+    withHeight(opts, height);
+    opts.series = [];
+    const chart = new ApexCharts(elm.current, opts);
+    chart.render();
+
+    BusChannel[idx] = async (_date) => {
+      const value = await fetchHistogram(metrics[0]);
+      if (!value) {
+        return;
+      }
+      const series = value.bounds
+        .map((_b, i) => ({
+          name: bucketLabel(value.bounds, i, unit),
+          data: value.samples.map((s) => ({
+            x: moment(new Date(s.ts)).format("HH:mm:ss"),
+            y: s.counts[i],
+          })),
+        }))
+        .reverse();
+      chart.updateOptions({ series }, false, false);
+    };
+
+    return () => {
+      delete BusChannel[idx];
+    };
+  }, [elm, metrics]);
+
+  return html`<div class="box columnbox mt-4">
+    <div class="header">
+      <h3 class="title">${desc || metrics?.join(",")}</h3>
+    </div>
+    <div ref=${elm}></div>
+  </div>`;
+};
+
+// Key polled for a stat tile, quantiles are computed by the server
+// The rates and quantiles are computed by the server over its history of the metric
+const statKey = ({ metric, value }) => {
+  if (value === "Rate") {
+    return "rate(" + metric + ")";
+  }
+  return value?.Quantile !== undefined
+    ? "quantile(" + value.Quantile + ", " + metric + ")"
+    : metric;
+};
+
+const chartKeys = ({ type, meta }) => {
+  switch (type) {
+    case "Stat":
+      return [statKey(meta)];
+    case "Gauge":
+      return [meta.metric];
+    case "Histogram":
+    case "Heatmap":
+    case "Table":
+    case "Scatter":
+    case "Text":
+      return [];
+    default:
+      return meta.metrics;
+  }
+};
 
-      const options = {
-        series: [
+const StatChart = ({ idx, meta, height }) => {
+  const elm = useRef(null);
+  const [value, setValue] = useState();
+  useEffect(() => {
+    if (!elm) {
+      return;
+    }
+    const key = statKey(meta);
+    const data = [];
+    const opts = Object.assign({}, window.ApexOptionsSparkline);
+    withHeight(opts, height);
+    withUnit(opts, meta.unit, meta.metric);
+    opts.series = [{ name: meta.desc, data }];
+    const chart = new ApexCharts(elm.current, opts);
+    chart.render();
+
+    BusChannel[idx] = (date) => {
+      const value = CachedChannel[key] ? CachedChannel[key][1] : null;
+      if (value === null || value === undefined) {
+        return;
+      }
+      setValue(value);
+      data.push([date, value]);
+      if (data.length > 60) {
+        data.shift();
+      }
+      chart.updateSeries([{ name: meta.desc, data }], false);
+    };
+
+    return () => {
+      delete BusChannel[idx];
+    };
+  }, [elm, meta]);
+
+  const color = thresholdColor(meta.thresholds, value);
+
+  return html`<div class="box stat mt-4">
+    <h3 class="title">${meta.desc || meta.metric}</h3>
+    <h2 class="stat-value" style=${color ? "color: " + color : ""}>
+      ${formatValue(value, meta.unit, meta.metric)}
+    </h2>
+    <div ref=${elm}></div>
+  </div>`;
+};
+
+// Color of the greatest threshold reached by the value
+const thresholdColor = (thresholds, value) =>
+  (thresholds || [])
+    .filter((t) => value !== undefined && value >= t.value)
+    .reduce((res, t) => (res && res.value > t.value ? res : t), null)?.color;
+
+const GaugeChart = ({ idx, meta, height }) => {
+  const elm = useRef(null);
+  const [value, setValue] = useState();
+  useEffect(() => {
+    if (!elm) {
+      return;
+    }
+    const min = meta.min ?? 0;
+    const max = meta.max ?? 100;
+    const opts = Object.assign({}, window.ApexOptionsGauge);
+    withHeight(opts, height);
+    opts.series = [0];
+    opts.labels = [meta.desc || meta.metric];
+    const chart = new ApexCharts(elm.current, opts);
+    chart.render();
+
+    BusChannel[idx] = (_date) => {
+      const value = CachedChannel[meta.metric]
+        ? CachedChannel[meta.metric][1]
+        : 0;
+      setValue(value);
+      const percent = ((value - min) / (max - min)) * 100;
+      chart.updateOptions(
+        {
+          series: [Math.max(0, Math.min(100, percent))],
+          colors: [thresholdColor(meta.thresholds, value) || "#17ead9"],
+          plotOptions: {
+            radialBar: {
+              dataLabels: {
+                value: {
+                  formatter: () => formatValue(value, meta.unit, meta.metric),
+                },
+              },
+            },
+          },
+        },
+        false,
+        false
+      );
+    };
+
+    return () => {
+      delete BusChannel[idx];
+    };
+  }, [elm, meta]);
+
+  return html`<div class="box columnbox mt-4">
+    <div class="header">
+      <h3 class="title">${meta.desc || meta.metric}</h3>
+    </div>
+    <div ref=${elm}></div>
+  </div>`;
+};
+
+const fetchTable = async (key, sort, descending, limit) => {
+  const res = await fetch(
+    "api/table?key=" +
+      encodeURIComponent(key) +
+      "&sort=" +
+      encodeURIComponent(sort) +
+      "&descending=" +
+      descending +
+      "&limit=" +
+      limit
+  );
+  if (!res.ok) {
+    return null;
+  }
+  return await res.json();
+};
+
+const TableChart = ({ idx, meta, height }) => {
+  const [table, setTable] = useState();
+  const [order, setOrder] = useState({
+    sort: meta.sort || "value",
+    descending: meta.descending,
+  });
+  const orderRef = useRef(order);
+
+  const load = async () => {
+    const { sort, descending } = orderRef.current;
+    const value = await fetchTable(meta.metric, sort, descending, meta.limit);
+    if (value) {
+      setTable(value);
+    }
+  };
+
+  useEffect(() => {
+    BusChannel[idx] = (_date) => load();
+    return () => {
+      delete BusChannel[idx];
+    };
+  }, [meta]);
+
+  // The server only sends the top rows, so sorting fetches the table again
+  const sortBy = (column) => {
+    const current = orderRef.current;
+    const next = {
+      sort: column,
+      descending: current.sort === column ? !current.descending : true,
+    };
+    orderRef.current = next;
+    setOrder(next);
+    load();
+  };
+
+  const header = (column, label) => html`<th
+    class="sortable"
+    onClick=${() => sortBy(column)}
+  >
+    ${label}
+    ${order.sort === column ? (order.descending ? " ▼" : " ▲") : ""}
+  </th>`;
+
+  const labels = table?.labels || [];
+  return html`<div class="box columnbox mt-4">
+    <div class="header">
+      <h3 class="title">${meta.desc || meta.metric}</h3>
+      ${table &&
+      table.total > table.rows.length &&
+      html`<h2 class="subtitle">
+        top ${table.rows.length} of ${table.total} series
+      </h2>`}
+    </div>
+    <div
+      class="series-table-wrapper"
+      style=${height ? "max-height: " + height + "px" : ""}
+    >
+      <table class="table table-dark table-sm series-table">
+        <thead>
+          <tr>
+            ${labels.map((l) => header(l, l))} ${header("value", "Value")}
+            ${header("rate", "Rate /s")} ${header("min", "Min")}
+            ${header("max", "Max")}
+          </tr>
+        </thead>
+        <tbody>
+          ${table?.rows.map(
+            (row) => html`<tr>
+              ${labels.map((l) => html`<td>${row.labels[l] ?? ""}</td>`)}
+              <td>${formatValue(row.value, meta.unit, meta.metric)}</td>
+              <td>${formatValue(row.rate, undefined, meta.metric)}</td>
+              <td>${formatValue(row.min, meta.unit, meta.metric)}</td>
+              <td>${formatValue(row.max, meta.unit, meta.metric)}</td>
+            </tr>`
+          )}
+        </tbody>
+      </table>
+    </div>
+  </div>`;
+};
+
+const fetchScatter = async (x, y, trend) => {
+  const res = await fetch(
+    "api/scatter?x=" +
+      encodeURIComponent(x) +
+      "&y=" +
+      encodeURIComponent(y) +
+      "&trend=" +
+      trend
+  );
+  if (!res.ok) {
+    return null;
+  }
+  return await res.json();
+};
+
+// Points of the trend line at the ends of the x values
+const trendPoints = (points, trend) => {
+  if (!trend || points.length === 0) {
+    return [];
+  }
+  const xs = points.map((p) => p[0]);
+  return [Math.min(...xs), Math.max(...xs)].map((x) => [
+    x,
+    trend.slope * x + trend.intercept,
+  ]);
+};
+
+const ScatterChart = ({ idx, meta, height }) => {
+  const elm = useRef(null);
+  const [correlation, setCorrelation] = useState();
+  useEffect(() => {
+    if (!elm) {
+      return;
+    }
+    const [x, y] = meta.metrics;
+    const opts = Object.assign({}, window.ApexOptionsScatter);
+    withHeight(opts, height);
+    withUnit(opts, meta.y_unit, y);
+    opts.xaxis = Object.assign({}, opts.xaxis, {
+      title: { text: x },
+      labels: { formatter: (value) => formatValue(value, meta.x_unit, x) },
+    });
+    opts.yaxis.title = { text: y };
+    opts.series = [];
+    const chart = new ApexCharts(elm.current, opts);
+    chart.render();
+
+    BusChannel[idx] = async (_date) => {
+      const value = await fetchScatter(x, y, !!meta.trend);
+      if (!value) {
+        return;
+      }
+      setCorrelation(value.correlation);
+      chart.updateSeries(
+        [
+          { name: y, type: "scatter", data: value.points },
           {
-            data: metrics?.map((m) => {
-              const value = CachedChannel[m] ? CachedChannel[m][1] : 0;
-              return {
-                x: m,
-                y: value,
-              };
-            }),
+            name: "trend",
+            type: "line",
+            data: trendPoints(value.points, value.trend),
           },
         ],
-      };
-      chart.updateOptions(options, false, false);
+        false
+      );
     };
 
     return () => {
       delete BusChannel[idx];
     };
-  }, [elm, metrics]);
+  }, [elm, meta]);
 
-  return html` <div class="col-md-4">
-    <div class="box columnbox mt-4">
-      <div class="header">
-        <h3 class="title">${desc || metrics?.join(",")}</h3>
-        ${metrics?.length === 1 &&
-        html`<h2 class="subtitle">${value || "--"} ${unit ? unit : ""}</h2>`}
-      </div>
-      <div ref=${elm}></div>
+  return html`<div class="box columnbox mt-4">
+    <div class="header">
+      <h3 class="title">${meta.desc || meta.metrics.join(" / ")}</h3>
+      ${meta.trend &&
+      correlation !== undefined &&
+      correlation !== null &&
+      html`<h2 class="subtitle">r = ${correlation.toFixed(2)}</h2>`}
     </div>
+    <div ref=${elm}></div>
+  </div>`;
+};
+
+const fetchText = async (template) => {
+  const res = await fetch("api/text?template=" + encodeURIComponent(template));
+  if (!res.ok) {
+    return null;
+  }
+  return await res.json();
+};
+
+// Markdown with the placeholders filled by the server, sanitized before being shown
+const TextChart = ({ idx, meta, height }) => {
+  const [text, setText] = useState(meta.content);
+  useEffect(() => {
+    BusChannel[idx] = async (_date) => {
+      const value = await fetchText(meta.content);
+      if (value) {
+        setText(value.text);
+      }
+    };
+    return () => {
+      delete BusChannel[idx];
+    };
+  }, [meta]);
+
+  return html`<div class="box columnbox mt-4">
+    ${meta.desc &&
+    html`<div class="header">
+      <h3 class="title">${meta.desc}</h3>
+    </div>`}
+    <div
+      class="markdown"
+      style=${height ? "max-height: " + height + "px" : ""}
+      dangerouslySetInnerHTML=${{
+        __html: DOMPurify.sanitize(marked.parse(text || "")),
+      }}
+    ></div>
   </div>`;
 };
 
-function renderChart({ idx, chartType, meta }) {
+function renderChart({ idx, chartType, meta, height }) {
   switch (chartType) {
+    case "Stat":
+      return html`<${StatChart} idx=${idx} meta=${meta} height=${height} />`;
+    case "Gauge":
+      return html`<${GaugeChart} idx=${idx} meta=${meta} height=${height} />`;
+    case "Table":
+      return html`<${TableChart} idx=${idx} meta=${meta} height=${height} />`;
+    case "Text":
+      return html`<${TextChart} idx=${idx} meta=${meta} height=${height} />`;
+    case "Scatter":
+      return html`<${ScatterChart} idx=${idx} meta=${meta} height=${height} />`;
+    case "StackedArea":
+      return html`<${StackedAreaChart}
+        idx=${idx}
+        meta=${meta}
+        height=${height}
+      />`;
+    case "Pie":
+      return html`<${PieChart} idx=${idx} meta=${meta} height=${height} />`;
+    case "Histogram":
+      return html`<${HistogramChart}
+        idx=${idx}
+        metrics=${meta.metrics}
+        desc=${meta.desc}
+        unit=${meta.unit}
+        height=${height}
+      />`;
+    case "Heatmap":
+      return html`<${HeatmapChart}
+        idx=${idx}
+        metrics=${meta.metrics}
+        desc=${meta.desc}
+        unit=${meta.unit}
+        height=${height}
+      />`;
     case "Bar":
       return html`<${BarChart}
         idx=${idx}
         metrics=${meta.metrics}
         desc=${meta.desc}
         unit=${meta.unit}
+        axis=${meta.axis}
+        styles=${meta.styles}
+        height=${height}
       />`;
     case "Line":
     default:
@@ -161,55 +901,316 @@ function renderChart({ idx, chartType, meta }) {
         metrics=${meta.metrics}
         desc=${meta.desc}
         unit=${meta.unit}
+        axis=${meta.axis}
+        styles=${meta.styles}
+        height=${height}
       />`;
   }
 }
 
-function App() {
-  const [charts, setCharts] = useState([]);
-  useEffect(async () => {
-    const chartres = await fetch("api/charts");
-    const charts = await chartres.json();
-    const metricres = await fetch("api/metrics");
-    const metrics = await metricres.json();
-    metrics.map((m) => {
-      Metrics[m.key] = m;
+// Width in grid columns of the charts without a configured width
+const defaultWidth = (chartType) => {
+  switch (chartType) {
+    case "Stat":
+      return 3;
+    case "Table":
+      return 8;
+    default:
+      return 4;
+  }
+};
+
+// Sends a chart edit, returning the error message of a rejected edit
+const editChart = async (method, path, body) => {
+  const res = await fetch(path, {
+    method,
+    headers: { "content-type": "application/json" },
+    body: body && JSON.stringify(body),
+  });
+  return res.ok ? null : (await res.text()) || res.statusText;
+};
+
+// Options of a chart as sent to the edit endpoints
+const panelBody = ({ type, meta, width, height }) => ({
+  type,
+  meta,
+  width,
+  height,
+});
+
+const NEW_CHART = {
+  type: "Line",
+  meta: { metrics: [""], desc: "", unit: "" },
+  width: null,
+  height: null,
+};
+
+const ChartEditor = ({ panel, dashboard, section, onDone }) => {
+  const [text, setText] = useState(
+    JSON.stringify(panelBody(panel || NEW_CHART), null, 2)
+  );
+  const [error, setError] = useState();
+
+  const save = async () => {
+    let body;
+    try {
+      body = JSON.parse(text);
+    } catch (e) {
+      setError(e.message);
+      return;
+    }
+    const error = panel
+      ? await editChart("PUT", "api/charts/" + panel.id, body)
+      : await editChart(
+          "POST",
+          "api/charts?dashboard=" + encodeURIComponent(dashboard),
+          { ...body, section }
+        );
+    if (error) {
+      setError(error);
+      return;
+    }
+    onDone(true);
+  };
+
+  return html`<div class="box columnbox mt-4 chart-editor">
+    <textarea
+      rows="12"
+      value=${text}
+      onInput=${(e) => setText(e.target.value)}
+    ></textarea>
+    ${error && html`<div class="chart-editor-error">${error}</div>`}
+    <button class="btn btn-sm btn-primary" onClick=${save}>Save</button>
+    <button class="btn btn-sm btn-secondary" onClick=${() => onDone(false)}>
+      Cancel
+    </button>
+  </div>`;
+};
+
+// Move, edit and delete buttons of a chart in an editable dashboard
+const ChartToolbar = ({ panel, position, onEdit, onChange }) => {
+  const move = async (offset) => {
+    await editChart("PUT", "api/charts/" + panel.id, {
+      ...panelBody(panel),
+      position: Math.max(0, position + offset),
     });
-    setCharts(charts);
-
-    const rawKeys = charts.map((m) => m.meta.metrics).flat();
-    const keys = [...new Set(rawKeys)];
-    const load = async () => {
-      let now = new Date();
-      let res = await fetch("api/metrics_value?keys=" + keys?.join(";"));
-      let values = await res.json();
-      console.log("loaded", values);
-      values.map(({ key, value }) => {
-        CachedChannel[key] = [now, value];
+    onChange();
+  };
+  const remove = async () => {
+    if (confirm("Delete chart " + (panel.meta.desc || panel.type) + "?")) {
+      await editChart("DELETE", "api/charts/" + panel.id);
+      onChange();
+    }
+  };
+  return html`<div class="chart-toolbar">
+    <button title="Move left" onClick=${() => move(-1)}>◀</button>
+    <button title="Move right" onClick=${() => move(1)}>▶</button>
+    <button title="Edit" onClick=${onEdit}>✎</button>
+    <button title="Delete" onClick=${remove}>✕</button>
+  </div>`;
+};
+
+const SectionView = ({
+  sectionIdx,
+  section,
+  dashboard,
+  editable,
+  onChange,
+}) => {
+  const [collapsed, setCollapsed] = useState(section.collapsed);
+  // Identifier of the chart being edited, or "new" for an added chart
+  const [editing, setEditing] = useState(null);
+  const onDone = (changed) => {
+    setEditing(null);
+    if (changed) {
+      onChange();
+    }
+  };
+  // Positions of the edit endpoints only count the configured charts
+  const configured = section.charts.filter((c) => c.id !== undefined);
+
+  const charts = html`<div class="row">
+    ${section.charts.map(
+      (c, idx) => html`<div
+        key=${c.id ?? "default-" + idx}
+        class="col-md-${c.width || defaultWidth(c.type)}"
+      >
+        ${editable &&
+        c.id !== undefined &&
+        html`<${ChartToolbar}
+          panel=${c}
+          position=${configured.indexOf(c)}
+          onEdit=${() => setEditing(c.id)}
+          onChange=${onChange}
+        />`}
+        ${editing === c.id
+          ? html`<${ChartEditor} panel=${c} onDone=${onDone} />`
+          : renderChart({
+              idx: sectionIdx + "-" + idx,
+              chartType: c.type,
+              meta: c.meta,
+              height: c.height,
+            })}
+      </div>`
+    )}
+    ${editable &&
+    html`<div class="col-md-4">
+      ${editing === "new"
+        ? html`<${ChartEditor}
+            dashboard=${dashboard}
+            section=${section.title}
+            onDone=${onDone}
+          />`
+        : html`<button
+            class="btn btn-sm btn-outline-light mt-4 add-chart"
+            onClick=${() => setEditing("new")}
+          >
+            + Add chart
+          </button>`}
+    </div>`}
+  </div>`;
+
+  if (!section.title) {
+    return charts;
+  }
+  return html`<div class="section mt-4">
+    <h2 class="section-title" onClick=${() => setCollapsed(!collapsed)}>
+      ${collapsed ? "▶" : "▼"} ${section.title}
+      <span class="section-count">${section.charts.length} charts</span>
+    </h2>
+    ${!collapsed && charts}
+  </div>`;
+};
+
+// Name of the dashboard selected in the url, like `#HTTP`
+const hashDashboard = () => decodeURIComponent(location.hash.slice(1));
+
+const DashboardTabs = ({ dashboards, selected }) =>
+  dashboards.length > 1 &&
+  html`<ul class="nav nav-tabs dashboard-tabs">
+    ${dashboards.map(
+      (name) => html`<li class="nav-item">
+        <a
+          class="nav-link ${name === selected ? "active" : ""}"
+          href=${"#" + encodeURIComponent(name)}
+          >${name}</a
+        >
+      </li>`
+    )}
+  </ul>`;
+
+function App() {
+  const [sections, setSections] = useState([]);
+  const [editable, setEditable] = useState(false);
+  // Error of loading the persisted charts, which refuses the edits
+  const [persistError, setPersistError] = useState(null);
+  const [dashboards, setDashboards] = useState([]);
+  const [selected, setSelected] = useState(hashDashboard());
+  // Incremented to load the charts again after an edit
+  const [version, setVersion] = useState(0);
+  useEffect(() => {
+    let interval;
+    let cancelled = false;
+    const init = async () => {
+      const unitres = await fetch("api/units");
+      Object.assign(Units, await unitres.json());
+      const dashboardres = await fetch("api/dashboards");
+      const info = await dashboardres.json();
+      setEditable(info.editable);
+      setPersistError(info.persist_error ?? null);
+      setDashboards(info.dashboards);
+      const dashboard = info.dashboards.includes(selected)
+        ? selected
+        : info.dashboards[0];
+      if (dashboard !== selected) {
+        setSelected(dashboard);
+        return;
+      }
+      const chartres = await fetch(
+        "api/charts?dashboard=" + encodeURIComponent(dashboard)
+      );
+      const sections = chartres.ok ? await chartres.json() : [];
+      const charts = sections.flatMap((s) => s.charts);
+      const metricres = await fetch("api/metrics");
+      const metrics = await metricres.json();
+      metrics.map((m) => {
+        Metrics[m.key] = m;
       });
-      for (const idx in BusChannel) {
-        BusChannel[idx](now);
+      setSections(sections);
+
+      const rawKeys = charts.map(chartKeys).flat();
+      const keys = [...new Set(rawKeys)];
+      const load = async () => {
+        let now = new Date();
+        // Paged on the sequence numbers, to show the annotations posted late with an earlier `ts`
+        const annotationres = await fetch(
+          "api/annotations?after=" + (LastAnnotationId ?? 0)
+        );
+        const annotations = await annotationres.json();
+        const oldest =
+          LastAnnotationId === null ? now.getTime() - ANNOTATIONS_WINDOW_MS : 0;
+        Annotations.push(...annotations.filter((a) => a.ts >= oldest));
+        LastAnnotationId = Math.max(
+          LastAnnotationId ?? 0,
+          ...annotations.map((a) => a.id)
+        );
+        let res = await fetch(
+          "api/metrics_value?keys=" + encodeURIComponent(keys?.join(";"))
+        );
+        let values = await res.json();
+        console.log("loaded", values);
+        values.map(({ key, value, series }) => {
+          CachedChannel[key] = [now, value];
+          if (series) {
+            SeriesChannel[key] = series.map((s) => s.key);
+            series.map((s) => {
+              CachedChannel[s.key] = [now, s.value];
+            });
+          }
+        });
+        for (const idx in BusChannel) {
+          BusChannel[idx](now);
+        }
+      };
+      if (cancelled) {
+        return;
       }
+      load();
+      interval = setInterval(load, 5000);
     };
-    load();
-    const interval = setInterval(load, 5000);
+    init();
 
     return () => {
+      cancelled = true;
       clearInterval(interval);
     };
+  }, [version, selected]);
+
+  useEffect(() => {
+    const onHashChange = () => setSelected(hashDashboard());
+    window.addEventListener("hashchange", onHashChange);
+    return () => window.removeEventListener("hashchange", onHashChange);
   }, []);
 
+  const onChange = () => setVersion((version) => version + 1);
+
   return html` <div id="wrapper">
     <div class="content-area">
       <div class="container-fluid">
         <div class="main">
-          <div class="row mt-4">
-            ${charts.map((c, idx) =>
-              renderChart({
-                idx,
-                chartType: c.type,
-                meta: c.meta,
-              })
+          <${DashboardTabs} dashboards=${dashboards} selected=${selected} />
+          ${persistError &&
+          html`<div class="alert alert-warning mt-3">${persistError}</div>`}
+          <div class="mt-4" key=${selected + "-" + version}>
+            ${sections.map(
+              (section, idx) =>
+                html`<${SectionView}
+                  sectionIdx=${idx}
+                  section=${section}
+                  dashboard=${selected}
+                  editable=${editable}
+                  onChange=${onChange}
+                />`
             )}
           </div>
         </div>
@@ -0,0 +1,317 @@
+use std::fmt;
+#[cfg(any(feature = "json", feature = "yaml", feature = "toml"))]
+use std::path::Path;
+
+use crate::{
+    metric_filter::MetricPattern, recorder::series::ValueKey, text::placeholders, Axis, ChartType,
+    DashboardOptions, Panel, Section, StatValue, MAIN_DASHBOARD,
+};
+
+/// Decimal places a `f64` can show.
+const MAX_PRECISION: u8 = 15;
+
+/// Error of loading or validating [`DashboardOptions`].
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    /// The file extension is not a supported format, or the feature of the format is not enabled.
+    UnsupportedFormat(String),
+    /// The file is not valid options, `path` locates the value like `custom_charts[1].meta.value`.
+    /// The charts of sections are only located as a whole, like `sections[0].charts[2]`.
+    Parse {
+        path: String,
+        message: String,
+    },
+    /// A chart has invalid options, `chart` locates it like `custom_charts[1]`.
+    InvalidChart {
+        chart: String,
+        message: String,
+    },
+    /// A dashboard has an invalid or duplicated name, `dashboard` locates it like `dashboards[1]`.
+    InvalidDashboard {
+        dashboard: String,
+        message: String,
+    },
+    /// The display of a metric is invalid, `metric` locates it like `metric_display.name`.
+    InvalidDisplay {
+        metric: String,
+        message: String,
+    },
+    /// A metric pattern is not a valid selector, `pattern` locates it like `exclude_metrics[0]`.
+    InvalidPattern {
+        pattern: String,
+        message: String,
+    },
+    /// The file of the charts edited at runtime is not valid, so it is not overwritten.
+    Persisted {
+        path: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "failed to read dashboard options: {err}"),
+            ConfigError::UnsupportedFormat(format) => {
+                write!(f, "unsupported dashboard options format: {format}")
+            }
+            ConfigError::Parse { path, message } => write!(f, "{path}: {message}"),
+            ConfigError::InvalidChart { chart, message } => write!(f, "{chart}: {message}"),
+            ConfigError::InvalidDashboard { dashboard, message } => {
+                write!(f, "{dashboard}: {message}")
+            }
+            ConfigError::InvalidDisplay { metric, message } => write!(f, "{metric}: {message}"),
+            ConfigError::InvalidPattern { pattern, message } => write!(f, "{pattern}: {message}"),
+            ConfigError::Persisted { path, message } => {
+                write!(f, "failed to load the persisted charts {path}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl DashboardOptions {
+    /// Loads and validates the options from a file, whose format is found from the extension:
+    /// `.json`, `.yaml`, `.yml` or `.toml`, each enabled by the feature of the same name.
+    ///
+    /// The charts are written like the `/api/charts` responses, with the options left out
+    /// taking their default. Enum values with data are written as maps, like
+    /// `value: {Quantile: 0.99}`:
+    ///
+    /// ```yaml
+    /// include_default: true
+    /// custom_charts:
+    ///   - type: Line
+    ///     meta:
+    ///       metrics: [http_requests_total]
+    ///       desc: Http requests
+    /// ```
+    #[cfg(any(feature = "json", feature = "yaml", feature = "toml"))]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let options: Self = match extension.as_str() {
+            #[cfg(feature = "json")]
+            "json" => {
+                let mut de = serde_json::Deserializer::from_str(&content);
+                serde_path_to_error::deserialize(&mut de).map_err(parse_error)?
+            }
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => {
+                let de = serde_yaml::Deserializer::from_str(&content);
+                serde_path_to_error::deserialize(de).map_err(parse_error)?
+            }
+            #[cfg(feature = "toml")]
+            "toml" => {
+                let de = toml::Deserializer::new(&content);
+                serde_path_to_error::deserialize(de).map_err(parse_error)?
+            }
+            _ => return Err(ConfigError::UnsupportedFormat(extension)),
+        };
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Checks the options of every chart and dashboard, returning the first invalid one.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (field, patterns) in [
+            ("include_metrics", &self.include_metrics),
+            ("exclude_metrics", &self.exclude_metrics),
+        ] {
+            for (i, pattern) in patterns.iter().enumerate() {
+                if MetricPattern::parse(pattern).is_none() {
+                    return Err(ConfigError::InvalidPattern {
+                        pattern: format!("{field}[{i}]"),
+                        message: format!("invalid pattern \"{pattern}\""),
+                    });
+                }
+            }
+        }
+        for (name, display) in self.metric_display.iter() {
+            let invalid = |message: String| ConfigError::InvalidDisplay {
+                metric: format!("metric_display.{name}"),
+                message,
+            };
+            match (display.precision, display.scale) {
+                (Some(precision), _) if precision > MAX_PRECISION => {
+                    return Err(invalid(format!(
+                        "precision {precision} is greater than {MAX_PRECISION}"
+                    )))
+                }
+                (_, Some(scale)) if scale == 0.0 || !scale.is_finite() => {
+                    return Err(invalid(format!(
+                        "scale {scale} is not a finite non-zero number"
+                    )))
+                }
+                _ => {}
+            }
+        }
+        validate_charts("", &self.custom_charts, &self.sections)?;
+        let mut names = vec![MAIN_DASHBOARD];
+        for (i, dashboard) in self.dashboards.iter().enumerate() {
+            let location = format!("dashboards[{i}]");
+            let invalid = |message: String| ConfigError::InvalidDashboard {
+                dashboard: location.clone(),
+                message,
+            };
+            if dashboard.name.is_empty() {
+                return Err(invalid("no name".to_string()));
+            }
+            if names.contains(&dashboard.name.as_str()) {
+                return Err(invalid(format!("duplicated name \"{}\"", dashboard.name)));
+            }
+            names.push(&dashboard.name);
+            validate_charts(
+                &format!("{location}."),
+                &dashboard.custom_charts,
+                &dashboard.sections,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the charts of a dashboard, whose location in the options starts with `prefix`.
+fn validate_charts(
+    prefix: &str,
+    custom_charts: &[ChartType],
+    sections: &[Section],
+) -> Result<(), ConfigError> {
+    for (i, chart) in custom_charts.iter().enumerate() {
+        validate_chart(chart).map_err(|message| ConfigError::InvalidChart {
+            chart: format!("{prefix}custom_charts[{i}]"),
+            message,
+        })?;
+    }
+    for (i, section) in sections.iter().enumerate() {
+        for (j, panel) in section.charts.iter().enumerate() {
+            validate_panel(panel).map_err(|message| ConfigError::InvalidChart {
+                chart: format!("{prefix}sections[{i}].charts[{j}]"),
+                message,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_panel(panel: &Panel) -> Result<(), String> {
+    validate_chart(&panel.chart)?;
+    match panel.width {
+        Some(width) if !(1..=12).contains(&width) => {
+            Err(format!("width {width} is not between 1 and 12"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(any(feature = "json", feature = "yaml", feature = "toml"))]
+fn parse_error<E: fmt::Display>(err: serde_path_to_error::Error<E>) -> ConfigError {
+    let path = err.path().to_string();
+    let message = err.inner().to_string();
+    // Some formats already prefix their messages with the path
+    let message = match message.strip_prefix(&format!("{path}: ")) {
+        Some(message) => message.to_string(),
+        None => message,
+    };
+    ConfigError::Parse { path, message }
+}
+
+fn validate_chart(chart: &ChartType) -> Result<(), String> {
+    let name = match chart {
+        ChartType::Stat { desc, .. }
+        | ChartType::Gauge { desc, .. }
+        | ChartType::Table { desc, .. }
+        | ChartType::Line { desc, .. }
+        | ChartType::Bar { desc, .. }
+        | ChartType::StackedArea { desc, .. }
+        | ChartType::Pie { desc, .. }
+        | ChartType::Histogram { desc, .. }
+        | ChartType::Heatmap { desc, .. }
+        | ChartType::Scatter { desc, .. }
+        | ChartType::Text { desc, .. } => desc,
+    };
+    let invalid = |message: String| match name.is_empty() {
+        true => message,
+        false => format!("{message} in chart \"{name}\""),
+    };
+
+    if let ChartType::Text { content, .. } = chart {
+        let invalid_key = |key: &&str| match ValueKey::parse(key) {
+            Some(key) => key.filters().iter().any(|f| f.name.is_empty()),
+            None => true,
+        };
+        return match placeholders(content).into_iter().find(invalid_key) {
+            Some(key) => Err(invalid(format!("invalid placeholder \"{{{{{key}}}}}\""))),
+            None => Ok(()),
+        };
+    }
+    if chart.metrics().is_empty() {
+        return Err(invalid("no metrics".to_string()));
+    }
+    // Only the charts of series values take value functions like `rate(name)`
+    let functions = matches!(
+        chart,
+        ChartType::Line { .. }
+            | ChartType::Bar { .. }
+            | ChartType::StackedArea { .. }
+            | ChartType::Pie { .. }
+    );
+    for metric in chart.metrics() {
+        match ValueKey::parse(metric) {
+            Some(ValueKey::Series(filter)) if !filter.name.is_empty() => {}
+            Some(key) if functions && key.filters().iter().all(|f| !f.name.is_empty()) => {}
+            // Scatter charts pair the samples of each interval, which only exist for these
+            Some(ValueKey::Rate(filter) | ValueKey::Quantile(_, filter))
+                if matches!(chart, ChartType::Scatter { .. }) && !filter.name.is_empty() => {}
+            _ => return Err(invalid(format!("invalid metric \"{metric}\""))),
+        }
+    }
+    match chart {
+        ChartType::Stat {
+            value: StatValue::Quantile(q),
+            ..
+        } if !(0.0..=1.0).contains(q) => {
+            Err(invalid(format!("quantile {q} is not between 0 and 1")))
+        }
+        ChartType::Gauge {
+            min: Some(min),
+            max: Some(max),
+            ..
+        } if min >= max => Err(invalid(format!("min {min} is not less than max {max}"))),
+        ChartType::Line { axis, .. } | ChartType::Bar { axis, .. } => match axis {
+            Axis {
+                min: Some(min),
+                max: Some(max),
+                ..
+            } if min >= max => Err(invalid(format!("min {min} is not less than max {max}"))),
+            Axis {
+                log: true,
+                min: Some(min),
+                ..
+            } if *min <= 0.0 => Err(invalid(format!("min {min} of a log scale is not positive"))),
+            _ => Ok(()),
+        },
+        ChartType::Table { limit: 0, .. } => Err(invalid("limit is 0".to_string())),
+        _ => Ok(()),
+    }
+}
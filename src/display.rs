@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::recorder::MetricMeta;
+
+/// How a metric is shown in the dashboard, overriding what it was described with.
+/// The values exported to `/prometheus` are left as recorded.
+///
+/// ```rust
+/// use metrics_dashboard::{DashboardOptions, MetricDisplay};
+///
+/// let dashboard_options = DashboardOptions {
+///     metric_display: [(
+///         "queue_fill_ratio".to_string(),
+///         MetricDisplay {
+///             name: Some("Queue fill".to_string()),
+///             scale: Some(100.0),
+///             unit: Some("%".to_string()),
+///             precision: Some(1),
+///             ..Default::default()
+///         },
+///     )]
+///     .into(),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct MetricDisplay {
+    /// Name shown instead of the metric name, in the default charts and legends.
+    pub name: Option<String>,
+    /// Decimal places of the values, 2 by default.
+    pub precision: Option<u8>,
+    /// Factor the values are multiplied by, like `100.0` to show a ratio in percent.
+    pub scale: Option<f64>,
+    /// Unit shown instead of the described one.
+    pub unit: Option<String>,
+    /// Description shown instead of the described one.
+    pub desc: Option<String>,
+}
+
+impl MetricDisplay {
+    /// Overrides the description and unit of the metric, and adds its display name and precision.
+    pub(crate) fn apply(&self, meta: &mut MetricMeta) {
+        if let Some(desc) = &self.desc {
+            meta.desc = Some(desc.clone());
+        }
+        if let Some(unit) = &self.unit {
+            meta.unit = Some(unit.clone());
+        }
+        meta.display_name = self.name.clone();
+        meta.precision = self.precision;
+    }
+
+    /// Scales a value and rounds it to the precision.
+    pub(crate) fn value(&self, value: f64) -> f64 {
+        let value = value * self.scale.unwrap_or(1.0);
+        match self.precision {
+            Some(precision) => {
+                let factor = 10f64.powi(precision as i32);
+                (value * factor).round() / factor
+            }
+            None => value,
+        }
+    }
+}
@@ -0,0 +1,803 @@
+//! Export of a dashboard as a Grafana dashboard model, to import it in Grafana with a Prometheus
+//! data source scraping the `/prometheus` endpoint:
+//!
+//! ```rust,ignore
+//! let (recorder, route) = build_dashboard_route_with_recorder(options);
+//! let model = metrics_dashboard::grafana::dashboard_json(&recorder, None);
+//! ```
+//!
+//! The dashboard is also served as `/api/grafana.json?dashboard=name`.
+//! Sections become Grafana rows and each chart a panel with PromQL queries summing the
+//! series selected by its metrics, like the dashboard does.
+use std::collections::BTreeMap;
+
+use metrics::Unit;
+use serde_json::{json, Value};
+
+use crate::{
+    dashboard_sections,
+    recorder::{
+        series::{series_name, MatchOp, SeriesFilter, ValueKey},
+        DashboardRecorder, MetricMeta, MetricType,
+    },
+    units::unit_from_label,
+    Axis, ChartType, LineStyle, Panel, SeriesStyle, StatValue, Threshold,
+};
+
+/// Columns of the Grafana grid.
+const GRID_COLUMNS: u64 = 24;
+/// Height of a Grafana grid unit, in pixels.
+const GRID_UNIT_PX: u32 = 30;
+const DEFAULT_PANEL_HEIGHT: u64 = 8;
+/// Color of the values under the first threshold, like in the dashboard.
+const BASE_COLOR: &str = "#17ead9";
+const DATASOURCE: &str = "${datasource}";
+
+/// Builds the Grafana dashboard model of a dashboard, the first one by default.
+///
+/// # Returns
+///
+/// `None` if there is no dashboard with this name.
+pub fn dashboard_json(recorder: &DashboardRecorder, dashboard: Option<&str>) -> Option<Value> {
+    // Resolved first, so the title is the one of the exported dashboard
+    let title = recorder.charts.read().dashboard(dashboard)?.name.clone();
+    let sections = dashboard_sections(recorder, Some(&title))?;
+    let metas = recorder.metrics();
+
+    let mut panels = vec![];
+    let mut layout = GridLayout::default();
+    let mut next_id = 1;
+    for section in sections {
+        let mut section_panels = vec![];
+        let mut row = None;
+        if !section.title.is_empty() {
+            layout.new_row();
+            row = Some(json!({
+                "id": next_id,
+                "type": "row",
+                "title": section.title,
+                "collapsed": section.collapsed,
+                "gridPos": { "x": 0, "y": layout.y, "w": GRID_COLUMNS, "h": 1 },
+            }));
+            next_id += 1;
+            layout.y += 1;
+        }
+        for panel in section.charts {
+            let mut value = chart_panel(&panel.chart, &metas);
+            value["id"] = json!(next_id);
+            value["gridPos"] = layout.place(&panel);
+            value["datasource"] = json!({ "type": "prometheus", "uid": DATASOURCE });
+            next_id += 1;
+            section_panels.push(value);
+        }
+        match row {
+            // Grafana keeps the panels of a collapsed row inside it
+            Some(mut row) if section.collapsed => {
+                row["panels"] = json!(section_panels);
+                panels.push(row);
+            }
+            Some(mut row) => {
+                row["panels"] = json!([]);
+                panels.push(row);
+                panels.extend(section_panels);
+            }
+            None => panels.extend(section_panels),
+        }
+    }
+
+    Some(json!({
+        "title": title,
+        "tags": ["metrics-dashboard"],
+        "editable": true,
+        "schemaVersion": 39,
+        "refresh": "5s",
+        "time": { "from": "now-1h", "to": "now" },
+        "templating": {
+            "list": [{
+                "name": "datasource",
+                "label": "Data source",
+                "type": "datasource",
+                "query": "prometheus",
+            }],
+        },
+        "panels": panels,
+    }))
+}
+
+/// Places the panels from left to right, wrapping to a new line when the grid is full.
+#[derive(Default)]
+struct GridLayout {
+    x: u64,
+    y: u64,
+    line_height: u64,
+}
+
+impl GridLayout {
+    fn place(&mut self, panel: &Panel) -> Value {
+        let w = match panel.width {
+            Some(width) => width as u64 * 2,
+            None => default_width(&panel.chart),
+        };
+        let h = panel
+            .height
+            .map(|height| height.div_ceil(GRID_UNIT_PX) as u64 + 2)
+            .unwrap_or(DEFAULT_PANEL_HEIGHT);
+        if self.x + w > GRID_COLUMNS {
+            self.new_row();
+        }
+        let pos = json!({ "x": self.x, "y": self.y, "w": w, "h": h });
+        self.x += w;
+        self.line_height = self.line_height.max(h);
+        pos
+    }
+
+    fn new_row(&mut self) {
+        self.y += self.line_height;
+        self.x = 0;
+        self.line_height = 0;
+    }
+}
+
+/// Width in Grafana columns, twice the 12 columns width of the dashboard.
+fn default_width(chart: &ChartType) -> u64 {
+    match chart {
+        ChartType::Stat { .. } => 6,
+        ChartType::Table { .. } => 16,
+        _ => 8,
+    }
+}
+
+fn chart_panel(chart: &ChartType, metas: &[MetricMeta]) -> Value {
+    let unit = grafana_unit(chart_unit(chart, metas));
+    match chart {
+        ChartType::Line {
+            metrics,
+            desc,
+            axis,
+            styles,
+            ..
+        } => json!({
+            "type": "timeseries",
+            "title": desc,
+            "targets": series_targets(metrics, metas, false),
+            "fieldConfig": styled_field_config(unit, axis, metrics, styles),
+        }),
+        ChartType::Bar {
+            metrics,
+            desc,
+            axis,
+            styles,
+            ..
+        } => json!({
+            "type": "bargauge",
+            "title": desc,
+            "targets": series_targets(metrics, metas, true),
+            "options": { "orientation": "horizontal" },
+            "fieldConfig": styled_field_config(unit, axis, metrics, styles),
+        }),
+        ChartType::StackedArea {
+            metrics,
+            desc,
+            stacked,
+            percent,
+            ..
+        } => {
+            let stacking = match (stacked, percent) {
+                (_, true) => "percent",
+                (true, false) => "normal",
+                (false, false) => "none",
+            };
+            json!({
+                "type": "timeseries",
+                "title": desc,
+                "targets": series_targets(metrics, metas, false),
+                "fieldConfig": {
+                    "defaults": {
+                        "unit": if *percent { "percentunit".to_string() } else { unit },
+                        "custom": { "fillOpacity": 60, "stacking": { "mode": stacking } },
+                    },
+                },
+            })
+        }
+        ChartType::Pie {
+            metrics,
+            desc,
+            donut,
+            ..
+        } => json!({
+            "type": "piechart",
+            "title": desc,
+            "targets": series_targets(metrics, metas, true),
+            "options": { "pieType": if *donut { "donut" } else { "pie" } },
+            "fieldConfig": { "defaults": { "unit": unit } },
+        }),
+        ChartType::Histogram { metrics, desc, .. } => json!({
+            "type": "bargauge",
+            "title": desc,
+            "targets": metrics
+                .iter()
+                .enumerate()
+                .map(|(i, metric)| target(
+                    i,
+                    format!("sum by (le) (increase({}[$__range]))", selector(metric, "_bucket")),
+                    "{{le}}",
+                    true,
+                    "heatmap",
+                ))
+                .collect::<Vec<_>>(),
+            "fieldConfig": { "defaults": { "unit": "short" } },
+        }),
+        ChartType::Heatmap { metrics, desc, .. } => json!({
+            "type": "heatmap",
+            "title": desc,
+            "targets": metrics
+                .iter()
+                .enumerate()
+                .map(|(i, metric)| target(
+                    i,
+                    format!(
+                        "sum by (le) (increase({}[$__rate_interval]))",
+                        selector(metric, "_bucket"),
+                    ),
+                    "{{le}}",
+                    false,
+                    "heatmap",
+                ))
+                .collect::<Vec<_>>(),
+            "options": { "calculate": false, "yAxis": { "unit": unit } },
+        }),
+        ChartType::Stat {
+            metric,
+            desc,
+            value,
+            thresholds,
+            ..
+        } => {
+            let expr = match value {
+                StatValue::Current => series_expr(metric, metas),
+                StatValue::Rate => rate_expr(&metric_filter(metric), metas),
+                StatValue::Quantile(q) => quantile_expr(*q, &metric_filter(metric)),
+            };
+            json!({
+                "type": "stat",
+                "title": desc,
+                "targets": [target(0, expr, desc, false, "time_series")],
+                "options": { "graphMode": "area", "colorMode": "value" },
+                "fieldConfig": {
+                    "defaults": { "unit": unit, "thresholds": grafana_thresholds(thresholds) },
+                },
+            })
+        }
+        ChartType::Gauge {
+            metric,
+            desc,
+            min,
+            max,
+            thresholds,
+            ..
+        } => json!({
+            "type": "gauge",
+            "title": desc,
+            "targets": [target(0, series_expr(metric, metas), desc, false, "time_series")],
+            "fieldConfig": {
+                "defaults": {
+                    "unit": unit,
+                    "min": min,
+                    "max": max,
+                    "thresholds": grafana_thresholds(thresholds),
+                },
+            },
+        }),
+        ChartType::Table {
+            metric,
+            desc,
+            sort,
+            descending,
+            limit,
+            ..
+        } => {
+            let selector = selector(metric, count_suffix(metric, metas));
+            let expr = match (sort.as_str(), descending) {
+                ("value", true) => format!("topk({limit}, {selector})"),
+                ("value", false) => format!("bottomk({limit}, {selector})"),
+                _ => selector,
+            };
+            json!({
+                "type": "table",
+                "title": desc,
+                "targets": [target(0, expr, "", true, "table")],
+                "transformations": [{
+                    "id": "organize",
+                    "options": { "excludeByName": { "Time": true, "__name__": true } },
+                }],
+                "fieldConfig": { "defaults": { "unit": unit } },
+            })
+        }
+        // The x values are taken from the first query, the y values from the second one
+        ChartType::Scatter { metrics, desc, .. } => json!({
+            "type": "xychart",
+            "title": desc,
+            "targets": series_targets(metrics, metas, false),
+            "options": { "seriesMapping": "auto" },
+            "transformations": [{ "id": "joinByField", "options": { "byField": "Time" } }],
+            "fieldConfig": { "defaults": { "unit": unit } },
+        }),
+        // Grafana has no values to fill the placeholders with, they are kept as is
+        ChartType::Text { content, desc } => json!({
+            "type": "text",
+            "title": desc,
+            "options": { "mode": "markdown", "content": content },
+        }),
+    }
+}
+
+/// Field config of the axis and series styles of a line or bar chart. The styles apply to the
+/// series of the query of their metric.
+fn styled_field_config(
+    unit: String,
+    axis: &Axis,
+    metrics: &[String],
+    styles: &BTreeMap<String, SeriesStyle>,
+) -> Value {
+    let mut defaults = json!({ "unit": unit });
+    if let Some(min) = axis.min {
+        defaults["min"] = json!(min);
+    }
+    if let Some(max) = axis.max {
+        defaults["max"] = json!(max);
+    }
+    if axis.log {
+        defaults["custom"] = json!({ "scaleDistribution": { "type": "log", "log": 10 } });
+    }
+    let overrides: Vec<Value> = metrics
+        .iter()
+        .enumerate()
+        .filter_map(|(i, metric)| {
+            let style = styles.get(metric)?;
+            let mut properties = vec![];
+            if let Some(alias) = &style.alias {
+                properties.push(json!({ "id": "displayName", "value": alias }));
+            }
+            if let Some(color) = &style.color {
+                properties.push(json!({
+                    "id": "color",
+                    "value": { "mode": "fixed", "fixedColor": color },
+                }));
+            }
+            let dash = match style.line {
+                LineStyle::Solid => None,
+                LineStyle::Dashed => Some(json!({ "fill": "dash", "dash": [10, 10] })),
+                LineStyle::Dotted => Some(json!({ "fill": "dot", "dash": [0, 10] })),
+            };
+            if let Some(dash) = dash {
+                properties.push(json!({ "id": "custom.lineStyle", "value": dash }));
+            }
+            if style.secondary {
+                properties.push(json!({ "id": "custom.axisPlacement", "value": "right" }));
+                properties.push(json!({
+                    "id": "unit",
+                    "value": grafana_unit(&axis.secondary_unit),
+                }));
+            }
+            Some(json!({
+                "matcher": { "id": "byFrameRefID", "options": ref_id(i) },
+                "properties": properties,
+            }))
+        })
+        .collect();
+    json!({ "defaults": defaults, "overrides": overrides })
+}
+
+fn target(i: usize, expr: String, legend: &str, instant: bool, format: &str) -> Value {
+    json!({
+        "refId": ref_id(i),
+        "datasource": { "type": "prometheus", "uid": DATASOURCE },
+        "expr": expr,
+        "legendFormat": legend,
+        "instant": instant,
+        "range": !instant,
+        "format": format,
+    })
+}
+
+/// Targets summing the series of each metric, like the values of the dashboard charts.
+/// The series of a selector are drawn one by one, named by Grafana from their labels.
+fn series_targets(metrics: &[String], metas: &[MetricMeta], instant: bool) -> Vec<Value> {
+    metrics
+        .iter()
+        .enumerate()
+        .map(|(i, metric)| {
+            let expr = match ValueKey::parse(metric) {
+                Some(ValueKey::Series(filter)) if filter.is_selector() => {
+                    let expr = filter_selector(&filter, count_suffix(metric, metas));
+                    return target(i, expr, "", instant, "time_series");
+                }
+                Some(ValueKey::Quantile(q, filter)) => quantile_expr(q, &filter),
+                Some(ValueKey::Rate(filter)) => rate_expr(&filter, metas),
+                Some(ValueKey::Ratio(a, b)) => {
+                    format!("{} / {}", recent_expr(&a, metas), recent_expr(&b, metas))
+                }
+                Some(ValueKey::Percent(filter)) => {
+                    let all = SeriesFilter {
+                        name: filter.name.clone(),
+                        matchers: vec![],
+                    };
+                    format!(
+                        "100 * {} / {}",
+                        recent_expr(&filter, metas),
+                        recent_expr(&all, metas)
+                    )
+                }
+                Some(ValueKey::MovingAvg(filter, window)) => format!(
+                    "sum(avg_over_time({}[{window}s]))",
+                    filter_selector(&filter, count_suffix(&filter.name, metas))
+                ),
+                Some(ValueKey::Diff(filter)) => format!(
+                    "sum(delta({}[$__interval]))",
+                    filter_selector(&filter, count_suffix(&filter.name, metas))
+                ),
+                _ => series_expr(metric, metas),
+            };
+            target(i, expr, metric, instant, "time_series")
+        })
+        .collect()
+}
+
+/// Sum of the selected series, the values count for histograms.
+fn series_expr(metric: &str, metas: &[MetricMeta]) -> String {
+    format!("sum({})", selector(metric, count_suffix(metric, metas)))
+}
+
+/// Increase per second of the selected series, of the values count for histograms.
+fn rate_expr(filter: &SeriesFilter, metas: &[MetricMeta]) -> String {
+    format!(
+        "sum(rate({}[$__rate_interval]))",
+        filter_selector(filter, count_suffix(&filter.name, metas))
+    )
+}
+
+/// Sum of the selected gauges, or of the rates of counters and histograms, as compared by
+/// the `ratio` and `percent` keys.
+fn recent_expr(filter: &SeriesFilter, metas: &[MetricMeta]) -> String {
+    match metas.iter().find(|m| m.key == filter.name).map(|m| &m.typ) {
+        Some(MetricType::Gauge) => format!("sum({})", filter_selector(filter, "")),
+        _ => rate_expr(filter, metas),
+    }
+}
+
+fn quantile_expr(q: f64, filter: &SeriesFilter) -> String {
+    format!(
+        "histogram_quantile({q}, sum by (le) (rate({}[$__rate_interval])))",
+        filter_selector(filter, "_bucket")
+    )
+}
+
+/// Suffix of the Prometheus series holding the value of a metric.
+fn count_suffix(metric: &str, metas: &[MetricMeta]) -> &'static str {
+    let name = series_name(metric);
+    match metas.iter().find(|m| m.key == name).map(|m| &m.typ) {
+        Some(MetricType::Histogram) => "_count",
+        _ => "",
+    }
+}
+
+/// Renders a metric like `name{label="value"}` as a PromQL selector of the series
+/// exposed by `/prometheus`, with `suffix` appended to the name.
+/// Glob values are rendered as regex matchers.
+fn selector(metric: &str, suffix: &str) -> String {
+    filter_selector(&metric_filter(metric), suffix)
+}
+
+/// Parses the series filter of a metric, taking it as a plain name when it is not valid.
+fn metric_filter(metric: &str) -> SeriesFilter {
+    SeriesFilter::parse(metric).unwrap_or_else(|| SeriesFilter {
+        name: metric.to_string(),
+        matchers: vec![],
+    })
+}
+
+fn filter_selector(filter: &SeriesFilter, suffix: &str) -> String {
+    let name = format!("{}{suffix}", prometheus_name(&filter.name));
+    if filter.matchers.is_empty() {
+        return name;
+    }
+    let matchers = filter
+        .matchers
+        .iter()
+        .map(|m| {
+            let (op, value) = match (m.op, m.is_glob()) {
+                (MatchOp::Equal, true) => ("=~", glob_regex(&m.value)),
+                (MatchOp::NotEqual, true) => ("!~", glob_regex(&m.value)),
+                (MatchOp::Equal, false) => ("=", m.value.clone()),
+                (MatchOp::NotEqual, false) => ("!=", m.value.clone()),
+                (MatchOp::Regex, _) => ("=~", m.value.clone()),
+                (MatchOp::NotRegex, _) => ("!~", m.value.clone()),
+            };
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("{}{op}\"{value}\"", m.label)
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{name}{{{matchers}}}")
+}
+
+/// Translates a glob pattern, where `*` matches any characters and `?` one character, to a regex.
+fn glob_regex(pattern: &str) -> String {
+    pattern
+        .split('*')
+        .map(|part| {
+            part.split('?')
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+        .collect::<Vec<_>>()
+        .join(".*")
+}
+
+/// Replaces the characters not allowed in Prometheus metric names.
+fn prometheus_name(name: &str) -> String {
+    name.chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect()
+}
+
+fn ref_id(i: usize) -> String {
+    let letter = (b'A' + (i % 26) as u8) as char;
+    match i / 26 {
+        0 => letter.to_string(),
+        n => format!("{letter}{n}"),
+    }
+}
+
+/// Unit of a chart, or of its first metric when the chart has none.
+fn chart_unit<'a>(chart: &'a ChartType, metas: &'a [MetricMeta]) -> &'a str {
+    let unit = match chart {
+        ChartType::Line { unit, .. }
+        | ChartType::Bar { unit, .. }
+        | ChartType::StackedArea { unit, .. }
+        | ChartType::Pie { unit, .. }
+        | ChartType::Histogram { unit, .. }
+        | ChartType::Heatmap { unit, .. }
+        | ChartType::Stat { unit, .. }
+        | ChartType::Gauge { unit, .. }
+        | ChartType::Table { unit, .. } => unit.as_str(),
+        ChartType::Scatter { y_unit, .. } => y_unit.as_str(),
+        ChartType::Text { .. } => "",
+    };
+    if !unit.is_empty() {
+        return unit;
+    }
+    let name = chart
+        .metrics()
+        .first()
+        .map(|m| series_name(m))
+        .unwrap_or_default();
+    metas
+        .iter()
+        .find(|m| m.key == name)
+        .and_then(|m| m.unit.as_deref())
+        .unwrap_or_default()
+}
+
+/// Maps a unit label, either canonical like `ms` or named like `milliseconds`, to a Grafana unit.
+/// Other labels are shown as a suffix, and charts without a unit as short numbers.
+fn grafana_unit(label: &str) -> String {
+    if label.is_empty() {
+        return "short".to_string();
+    }
+    let grafana = match unit_from_label(label) {
+        Some(Unit::Count) => "short",
+        Some(Unit::Percent) => "percent",
+        Some(Unit::Seconds) => "s",
+        Some(Unit::Milliseconds) => "ms",
+        Some(Unit::Microseconds) => "µs",
+        Some(Unit::Nanoseconds) => "ns",
+        Some(Unit::Tebibytes) => "tbytes",
+        Some(Unit::Gigibytes) => "gbytes",
+        Some(Unit::Mebibytes) => "mbytes",
+        Some(Unit::Kibibytes) => "kbytes",
+        Some(Unit::Bytes) => "bytes",
+        Some(Unit::TerabitsPerSecond) => "Tbits",
+        Some(Unit::GigabitsPerSecond) => "Gbits",
+        Some(Unit::MegabitsPerSecond) => "Mbits",
+        Some(Unit::KilobitsPerSecond) => "Kbits",
+        Some(Unit::BitsPerSecond) => "bps",
+        Some(Unit::CountPerSecond) => "cps",
+        None => return format!("suffix: {label}"),
+    };
+    grafana.to_string()
+}
+
+fn grafana_thresholds(thresholds: &[Threshold]) -> Value {
+    let mut thresholds = thresholds.to_vec();
+    thresholds.sort_by(|a, b| a.value.total_cmp(&b.value));
+    let steps: Vec<Value> = std::iter::once(json!({ "color": BASE_COLOR, "value": null }))
+        .chain(
+            thresholds
+                .iter()
+                .map(|t| json!({ "color": t.color, "value": t.value })),
+        )
+        .collect();
+    json!({ "mode": "absolute", "steps": steps })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dashboard, DashboardOptions, Section, MAIN_DASHBOARD};
+
+    fn meta(key: &str, typ: MetricType) -> MetricMeta {
+        MetricMeta {
+            key: key.to_string(),
+            typ,
+            desc: None,
+            unit: None,
+            target: None,
+            display_name: None,
+            precision: None,
+        }
+    }
+
+    fn exprs(metrics: &[&str]) -> Vec<String> {
+        let metas = [
+            meta("requests", MetricType::Counter),
+            meta("latency", MetricType::Histogram),
+            meta("queue", MetricType::Gauge),
+        ];
+        let metrics: Vec<String> = metrics.iter().map(|m| m.to_string()).collect();
+        series_targets(&metrics, &metas, false)
+            .iter()
+            .map(|target| target["expr"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn rate_exprs() {
+        assert_eq!(
+            exprs(&["rate(requests)", "rate(latency)"]),
+            [
+                "sum(rate(requests[$__rate_interval]))",
+                "sum(rate(latency_count[$__rate_interval]))",
+            ]
+        );
+        assert_eq!(
+            exprs(&[
+                "ratio(requests{status=\"500\"}, requests)",
+                "ratio(queue, queue)"
+            ]),
+            [
+                "sum(rate(requests{status=\"500\"}[$__rate_interval])) / \
+                 sum(rate(requests[$__rate_interval]))",
+                "sum(queue) / sum(queue)",
+            ]
+        );
+    }
+
+    #[test]
+    fn quantile_exprs() {
+        assert_eq!(
+            exprs(&["quantile(0.99, latency{route=\"/users\"})"]),
+            ["histogram_quantile(0.99, sum by (le) \
+              (rate(latency_bucket{route=\"/users\"}[$__rate_interval])))"]
+        );
+    }
+
+    #[test]
+    fn selector_exprs() {
+        assert_eq!(
+            exprs(&[
+                "requests",
+                "requests{status=\"200\"}",
+                "latency{route=\"/api/*\"}",
+                "requests{status=~\"5..\", route!=\"/health\"}",
+            ]),
+            [
+                "sum(requests)",
+                "sum(requests{status=\"200\"})",
+                "latency_count{route=~\"/api/.*\"}",
+                "requests{route!=\"/health\",status=~\"5..\"}",
+            ]
+        );
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(grafana_unit(""), "short");
+        assert_eq!(grafana_unit("count"), "short");
+        assert_eq!(grafana_unit("seconds"), "s");
+        assert_eq!(grafana_unit("ms"), "ms");
+        assert_eq!(grafana_unit("req"), "suffix: req");
+    }
+
+    #[test]
+    fn grid_layout() {
+        let panel = |width: Option<u8>| Panel {
+            width,
+            ..Panel::new(ChartType::line(vec!["queue".to_string()], "", ""))
+        };
+        let mut layout = GridLayout::default();
+        let positions: Vec<Value> = [Some(6), Some(4), Some(4), None]
+            .into_iter()
+            .map(|width| layout.place(&panel(width)))
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                json!({ "x": 0, "y": 0, "w": 12, "h": 8 }),
+                json!({ "x": 12, "y": 0, "w": 8, "h": 8 }),
+                // Wraps when the line is full
+                json!({ "x": 0, "y": 8, "w": 8, "h": 8 }),
+                json!({ "x": 8, "y": 8, "w": 8, "h": 8 }),
+            ]
+        );
+        let tall = Panel {
+            height: Some(600),
+            ..panel(None)
+        };
+        assert_eq!(layout.place(&tall)["h"], 22);
+    }
+
+    #[test]
+    fn rows_of_sections() {
+        let chart = |metric: &str| Panel::new(ChartType::line(vec![metric.to_string()], "", ""));
+        let recorder = DashboardRecorder::new(DashboardOptions {
+            sections: vec![
+                Section {
+                    title: "Queue".to_string(),
+                    charts: vec![chart("queue"), chart("queue_wait")],
+                    ..Default::default()
+                },
+                Section {
+                    title: "Jobs".to_string(),
+                    collapsed: true,
+                    charts: vec![chart("jobs")],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        });
+        let model = dashboard_json(&recorder, None).expect("a dashboard");
+        assert_eq!(model["title"], MAIN_DASHBOARD);
+
+        let panels = model["panels"].as_array().expect("panels");
+        let layout: Vec<(&str, &Value)> = panels
+            .iter()
+            .map(|p| (p["type"].as_str().unwrap_or_default(), &p["gridPos"]))
+            .collect();
+        assert_eq!(
+            layout,
+            [
+                ("row", &json!({ "x": 0, "y": 0, "w": 24, "h": 1 })),
+                ("timeseries", &json!({ "x": 0, "y": 1, "w": 8, "h": 8 })),
+                ("timeseries", &json!({ "x": 8, "y": 1, "w": 8, "h": 8 })),
+                ("row", &json!({ "x": 0, "y": 9, "w": 24, "h": 1 })),
+            ]
+        );
+        // The panels of a collapsed row are kept inside it
+        assert_eq!(
+            panels[3]["panels"][0]["gridPos"],
+            json!({ "x": 0, "y": 10, "w": 8, "h": 8 })
+        );
+    }
+
+    #[test]
+    fn title_of_the_exported_dashboard() {
+        let recorder = DashboardRecorder::new(DashboardOptions {
+            dashboards: vec![Dashboard {
+                name: "Jobs".to_string(),
+                custom_charts: vec![ChartType::line(vec!["jobs".to_string()], "", "")],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let model = dashboard_json(&recorder, None).expect("a dashboard");
+        assert_eq!(model["title"], "Jobs");
+        assert_eq!(dashboard_json(&recorder, Some(MAIN_DASHBOARD)), None);
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use metrics::Unit;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    recorder::{MetricMeta, MetricType},
+    ChartType,
+};
+
+/// How the default charts of `include_default` are made for the metrics not shown in
+/// other charts.
+///
+/// With a grouping, the related metrics of the same type and unit share one chart:
+/// counters are drawn as rates, gauges as values and histograms as their p50 and p99.
+/// A metric alone in its group keeps its own chart.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DefaultGrouping {
+    /// One chart per metric.
+    #[default]
+    None,
+    /// Groups by name prefix, the part before the first `_` like `http` of `http_requests_total`.
+    Prefix,
+    /// Groups by unit.
+    Unit,
+    /// Groups by target of the code which registered the metrics, its module path by default.
+    Module,
+}
+
+/// Quantiles drawn for the histograms of a group.
+const GROUP_QUANTILES: [f64; 2] = [0.5, 0.99];
+
+/// Builds the default charts of the metrics, in the order of the groups.
+pub(crate) fn default_charts(metas: &[&MetricMeta], grouping: DefaultGrouping) -> Vec<ChartType> {
+    // Metrics by group, type and unit
+    let mut groups: BTreeMap<(String, u8, String), Vec<&MetricMeta>> = BTreeMap::new();
+    for (i, meta) in metas.iter().enumerate() {
+        let unit = meta.unit.clone().unwrap_or_default();
+        let group = match grouping {
+            DefaultGrouping::None => format!("{i:08}"),
+            DefaultGrouping::Prefix => meta
+                .key
+                .split_once('_')
+                .map(|(prefix, _)| prefix)
+                .unwrap_or(&meta.key)
+                .to_string(),
+            DefaultGrouping::Unit => match unit.is_empty() {
+                true => "count".to_string(),
+                false => unit.clone(),
+            },
+            DefaultGrouping::Module => meta.target.clone().unwrap_or_default(),
+        };
+        let typ = match meta.typ {
+            MetricType::Counter => 0,
+            MetricType::Gauge => 1,
+            MetricType::Histogram => 2,
+        };
+        groups.entry((group, typ, unit)).or_default().push(meta);
+    }
+
+    groups
+        .into_iter()
+        .map(|((group, _, unit), metas)| match metas.as_slice() {
+            [meta] => metric_chart(meta),
+            _ => group_chart(&group, &unit, &metas),
+        })
+        .collect()
+}
+
+/// The chart of a single metric.
+fn metric_chart(meta: &MetricMeta) -> ChartType {
+    let metrics = vec![meta.key.clone()];
+    let desc = meta
+        .display_name
+        .clone()
+        .or_else(|| meta.desc.clone())
+        .unwrap_or_else(|| meta.key.clone());
+    let unit = meta.unit.clone().unwrap_or_default();
+    match meta.typ {
+        MetricType::Histogram => ChartType::Histogram {
+            metrics,
+            desc,
+            unit,
+        },
+        MetricType::Gauge if unit == Unit::Percent.as_canonical_label() => ChartType::Gauge {
+            metric: meta.key.clone(),
+            desc,
+            unit: "".to_string(),
+            min: None,
+            max: None,
+            thresholds: vec![],
+        },
+        _ => ChartType::line(metrics, &desc, &unit),
+    }
+}
+
+/// The chart of metrics of the same type and unit.
+fn group_chart(group: &str, unit: &str, metas: &[&MetricMeta]) -> ChartType {
+    match metas[0].typ {
+        MetricType::Counter => ChartType::line(
+            metas.iter().map(|m| format!("rate({})", m.key)).collect(),
+            &format!("{group} per second"),
+            &format!("{unit}/s"),
+        ),
+        MetricType::Gauge => {
+            ChartType::line(metas.iter().map(|m| m.key.clone()).collect(), group, unit)
+        }
+        MetricType::Histogram => ChartType::line(
+            metas
+                .iter()
+                .flat_map(|m| {
+                    GROUP_QUANTILES
+                        .iter()
+                        .map(move |q| format!("quantile({q}, {})", m.key))
+                })
+                .collect(),
+            &format!("{group} p50 and p99"),
+            unit,
+        ),
+    }
+}
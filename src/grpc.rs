@@ -0,0 +1,368 @@
+//! Tower layer which records gRPC request metrics, usable with tonic servers:
+//!
+//! ```rust,ignore
+//! tonic::transport::Server::builder()
+//!     .layer(GrpcMetricLayer::new())
+//!     .add_service(service)
+//! ```
+//!
+//! Each call is counted with its method and gRPC status. Because a successful call reports its
+//! status in the trailers, the call is recorded when the response body is finished, so the
+//! duration covers streaming responses as well. A call dropped before its response or a body
+//! dropped before its trailers is counted as `CANCELLED`. Paths which are not a gRPC method
+//! like `/pkg.Service/Method` are counted with the `unknown` method.
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Once,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use http::{HeaderMap, Request, Response};
+use http_body::{Body, Frame, SizeHint};
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{register_default_charts, ChartType};
+
+pub const GRPC_REQUESTS_TOTAL: &str = "grpc_requests_total";
+pub const GRPC_REQUESTS_ERROR: &str = "grpc_requests_error";
+pub const GRPC_REQUESTS_DURATION_SECONDS: &str = "grpc_requests_duration_seconds";
+
+const GRPC_STATUS_HEADER: &str = "grpc-status";
+
+static DESCRIBE: Once = Once::new();
+
+fn describe_grpc_metrics() {
+    DESCRIBE.call_once(|| {
+        describe_counter!(GRPC_REQUESTS_TOTAL, Unit::Count, "gRPC requests");
+        describe_counter!(GRPC_REQUESTS_ERROR, Unit::Count, "gRPC requests not OK");
+        describe_histogram!(
+            GRPC_REQUESTS_DURATION_SECONDS,
+            Unit::Seconds,
+            "gRPC requests duration"
+        );
+    });
+}
+
+/// Layer which wraps a gRPC service with [`GrpcMetricService`].
+#[derive(Debug, Clone)]
+pub struct GrpcMetricLayer {
+    _priv: (),
+}
+
+impl GrpcMetricLayer {
+    /// Creates the layer and registers the gRPC chart group to the dashboard.
+    pub fn new() -> Self {
+        register_default_charts(
+            "grpc",
+            vec![
+                ChartType::line(
+                    vec![
+                        GRPC_REQUESTS_TOTAL.to_string(),
+                        GRPC_REQUESTS_ERROR.to_string(),
+                    ],
+                    "gRPC requests",
+                    Unit::Count.as_canonical_label(),
+                ),
+                ChartType::Heatmap {
+                    metrics: vec![GRPC_REQUESTS_DURATION_SECONDS.to_string()],
+                    desc: "gRPC requests duration".to_string(),
+                    unit: Unit::Seconds.as_canonical_label().to_string(),
+                },
+            ],
+        );
+        Self { _priv: () }
+    }
+}
+
+impl Default for GrpcMetricLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for GrpcMetricLayer {
+    type Service = GrpcMetricService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcMetricService { inner }
+    }
+}
+
+/// Service for `GrpcMetricLayer`.
+#[derive(Debug, Clone)]
+pub struct GrpcMetricService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for GrpcMetricService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = Response<GrpcMetricBody<ResBody>>;
+    type Error = S::Error;
+    type Future = GrpcMetricFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        describe_grpc_metrics();
+        let call = GrpcCall {
+            method: method_label(req.uri().path()),
+            started_at: Instant::now(),
+        };
+        GrpcMetricFuture {
+            inner: self.inner.call(req),
+            call: Some(call),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for `GrpcMetricService`.
+    pub struct GrpcMetricFuture<F> {
+        #[pin]
+        inner: F,
+        call: Option<GrpcCall>,
+    }
+
+    impl<F> PinnedDrop for GrpcMetricFuture<F> {
+        fn drop(this: Pin<&mut Self>) {
+            if let Some(call) = this.project().call.take() {
+                call.finish(GrpcCode::Cancelled as i32);
+            }
+        }
+    }
+}
+
+impl<F, ResBody, E> Future for GrpcMetricFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = Result<Response<GrpcMetricBody<ResBody>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = match this.inner.poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        let call = this.call.take().expect("Should poll only once after ready");
+        match res {
+            Ok(resp) => {
+                // Trailers-only responses carry the status in the headers
+                let call = match grpc_status(resp.headers()) {
+                    Some(status) => {
+                        call.finish(status);
+                        None
+                    }
+                    None => Some(call),
+                };
+                Poll::Ready(Ok(resp.map(|inner| GrpcMetricBody { inner, call })))
+            }
+            Err(err) => {
+                call.finish(GrpcCode::Unknown as i32);
+                Poll::Ready(Err(err))
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// Response body for `GrpcMetricService`, which records the call once the trailers are sent.
+    pub struct GrpcMetricBody<B> {
+        #[pin]
+        inner: B,
+        call: Option<GrpcCall>,
+    }
+
+    impl<B> PinnedDrop for GrpcMetricBody<B> {
+        fn drop(this: Pin<&mut Self>) {
+            if let Some(call) = this.project().call.take() {
+                call.finish(GrpcCode::Cancelled as i32);
+            }
+        }
+    }
+}
+
+impl<B: Body> Body for GrpcMetricBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let res = match this.inner.poll_frame(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        match &res {
+            Some(Ok(frame)) => {
+                if let Some(status) = frame.trailers_ref().and_then(grpc_status) {
+                    if let Some(call) = this.call.take() {
+                        call.finish(status);
+                    }
+                }
+            }
+            Some(Err(_)) => {
+                if let Some(call) = this.call.take() {
+                    call.finish(GrpcCode::Internal as i32);
+                }
+            }
+            None => {
+                if let Some(call) = this.call.take() {
+                    call.finish(GrpcCode::Unknown as i32);
+                }
+            }
+        }
+        Poll::Ready(res)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// A gRPC call in progress.
+#[derive(Debug)]
+struct GrpcCall {
+    method: String,
+    started_at: Instant,
+}
+
+impl GrpcCall {
+    fn finish(self, status: i32) {
+        let latency = self.started_at.elapsed();
+        let status = GrpcCode::from_i32(status).as_str();
+        counter!(GRPC_REQUESTS_TOTAL, "method" => self.method.clone(), "status" => status)
+            .increment(1);
+        if status != GrpcCode::Ok.as_str() {
+            counter!(GRPC_REQUESTS_ERROR, "method" => self.method.clone(), "status" => status)
+                .increment(1);
+        }
+        histogram!(GRPC_REQUESTS_DURATION_SECONDS, "method" => self.method)
+            .record(latency.as_secs_f64());
+    }
+}
+
+/// The method of a path like `/pkg.Service/Method`, or `unknown` to keep the label values bounded.
+fn method_label(path: &str) -> String {
+    let is_name = |name: &str| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+    };
+    match path.strip_prefix('/').and_then(|path| path.split_once('/')) {
+        Some((service, method)) if is_name(service) && is_name(method) && !method.contains('.') => {
+            format!("{service}/{method}")
+        }
+        _ => "unknown".to_string(),
+    }
+}
+
+fn grpc_status(headers: &HeaderMap) -> Option<i32> {
+    headers
+        .get(GRPC_STATUS_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+/// gRPC status codes, as defined in the gRPC specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GrpcCode {
+    Ok = 0,
+    Cancelled = 1,
+    Unknown = 2,
+    InvalidArgument = 3,
+    DeadlineExceeded = 4,
+    NotFound = 5,
+    AlreadyExists = 6,
+    PermissionDenied = 7,
+    ResourceExhausted = 8,
+    FailedPrecondition = 9,
+    Aborted = 10,
+    OutOfRange = 11,
+    Unimplemented = 12,
+    Internal = 13,
+    Unavailable = 14,
+    DataLoss = 15,
+    Unauthenticated = 16,
+}
+
+impl GrpcCode {
+    fn from_i32(code: i32) -> Self {
+        match code {
+            0 => Self::Ok,
+            1 => Self::Cancelled,
+            3 => Self::InvalidArgument,
+            4 => Self::DeadlineExceeded,
+            5 => Self::NotFound,
+            6 => Self::AlreadyExists,
+            7 => Self::PermissionDenied,
+            8 => Self::ResourceExhausted,
+            9 => Self::FailedPrecondition,
+            10 => Self::Aborted,
+            11 => Self::OutOfRange,
+            12 => Self::Unimplemented,
+            13 => Self::Internal,
+            14 => Self::Unavailable,
+            15 => Self::DataLoss,
+            16 => Self::Unauthenticated,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::Cancelled => "CANCELLED",
+            Self::Unknown => "UNKNOWN",
+            Self::InvalidArgument => "INVALID_ARGUMENT",
+            Self::DeadlineExceeded => "DEADLINE_EXCEEDED",
+            Self::NotFound => "NOT_FOUND",
+            Self::AlreadyExists => "ALREADY_EXISTS",
+            Self::PermissionDenied => "PERMISSION_DENIED",
+            Self::ResourceExhausted => "RESOURCE_EXHAUSTED",
+            Self::FailedPrecondition => "FAILED_PRECONDITION",
+            Self::Aborted => "ABORTED",
+            Self::OutOfRange => "OUT_OF_RANGE",
+            Self::Unimplemented => "UNIMPLEMENTED",
+            Self::Internal => "INTERNAL",
+            Self::Unavailable => "UNAVAILABLE",
+            Self::DataLoss => "DATA_LOSS",
+            Self::Unauthenticated => "UNAUTHENTICATED",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn method_labels() {
+        assert_eq!(
+            method_label("/grpc.health.v1.Health/Check"),
+            "grpc.health.v1.Health/Check"
+        );
+        assert_eq!(method_label("/Greeter/SayHello"), "Greeter/SayHello");
+        assert_eq!(method_label("/"), "unknown");
+        assert_eq!(method_label("/pkg.Service"), "unknown");
+        assert_eq!(method_label("/pkg.Service/"), "unknown");
+        assert_eq!(method_label("/pkg.Service/Method/extra"), "unknown");
+        assert_eq!(method_label("/wp-admin/index.php"), "unknown");
+    }
+}
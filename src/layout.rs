@@ -0,0 +1,351 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::validate_panel, ChartType, ConfigError, DashboardOptions};
+
+/// A named and collapsible row of charts in the dashboard.
+///
+/// Besides its own charts, a section takes the auto-generated charts of `include_default`
+/// matching its `include` patterns, in the order of the patterns:
+///
+/// ```rust
+/// use metrics_dashboard::{DashboardOptions, Section};
+///
+/// let dashboard_options = DashboardOptions {
+///     sections: vec![Section {
+///         title: "Http".to_string(),
+///         include: vec!["http".to_string(), "http_*".to_string()],
+///         ..Default::default()
+///     }],
+///     include_default: true,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Section {
+    pub title: String,
+    /// Whether the section starts collapsed.
+    pub collapsed: bool,
+    pub charts: Vec<Panel>,
+    /// Patterns of the default charts shown in this section, matched against the name of
+    /// a registered chart group or the metric names of a chart. `*` matches any characters.
+    pub include: Vec<String>,
+}
+
+/// A chart with its size in the dashboard.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Panel {
+    /// Identifier of a chart which can be edited at runtime, set by the dashboard.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub chart: ChartType,
+    /// Width in columns of a 12 columns grid, the default depends on the chart type.
+    #[serde(default)]
+    pub width: Option<u8>,
+    /// Height of the chart in pixels.
+    #[serde(default)]
+    pub height: Option<u32>,
+}
+
+impl Panel {
+    pub fn new(chart: ChartType) -> Self {
+        Self {
+            id: None,
+            chart,
+            width: None,
+            height: None,
+        }
+    }
+
+    pub fn with_width(mut self, width: u8) -> Self {
+        self.width = Some(width.clamp(1, 12));
+        self
+    }
+
+    pub fn with_height(mut self, height: u32) -> Self {
+        self.height = Some(height);
+        self
+    }
+}
+
+impl From<ChartType> for Panel {
+    fn from(chart: ChartType) -> Self {
+        Self::new(chart)
+    }
+}
+
+/// Where to put a chart added or moved at runtime.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ChartEdit {
+    #[serde(flatten)]
+    pub panel: Panel,
+    /// Title of the section, the untitled top section by default. A missing section is created.
+    pub section: Option<String>,
+    /// Position in the section, the end by default.
+    pub position: Option<usize>,
+}
+
+/// A named dashboard, shown in its own tab. Its options are the same as the main dashboard,
+/// which is named [`MAIN_DASHBOARD`].
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Dashboard {
+    pub name: String,
+    pub custom_charts: Vec<ChartType>,
+    pub include_default: bool,
+    pub sections: Vec<Section>,
+}
+
+/// Name of the dashboard of the top level `DashboardOptions` charts.
+pub const MAIN_DASHBOARD: &str = "Overview";
+
+/// The charts configured in the dashboards, which can be edited at runtime.
+/// In each dashboard, the custom charts are moved to a first untitled section.
+/// Chart identifiers are unique across the dashboards.
+#[derive(Debug)]
+pub(crate) struct ChartSet {
+    dashboards: Vec<Dashboard>,
+    next_id: u64,
+    persist_path: Option<PathBuf>,
+    /// Error of loading the persisted file. The edits are then refused so the charts saved
+    /// in the file are not overwritten.
+    persist_error: Option<String>,
+}
+
+impl ChartSet {
+    /// Creates the chart set from the options. The sections of the persisted file, if it exists,
+    /// replace the ones of the dashboards with the same names. A file which fails to load is
+    /// left untouched, the configured charts being used instead, see [`Self::persist_error`].
+    pub fn new(options: &DashboardOptions) -> Self {
+        let main = Dashboard {
+            name: MAIN_DASHBOARD.to_string(),
+            custom_charts: options.custom_charts.clone(),
+            include_default: options.include_default,
+            sections: options.sections.clone(),
+        };
+        // The main dashboard is omitted when empty, so the options can only hold named ones
+        let main_is_empty =
+            main.custom_charts.is_empty() && main.sections.is_empty() && !main.include_default;
+        let mut dashboards: Vec<Dashboard> = match main_is_empty && !options.dashboards.is_empty() {
+            true => options.dashboards.clone(),
+            false => std::iter::once(main)
+                .chain(options.dashboards.iter().cloned())
+                .collect(),
+        };
+
+        let (persisted, persist_error) = match &options.persist_path {
+            Some(path) => match load_persisted(path) {
+                Ok(persisted) => (persisted, None),
+                Err(message) => (vec![], Some(message)),
+            },
+            None => (vec![], None),
+        };
+
+        let mut next_id = 1;
+        for dashboard in dashboards.iter_mut() {
+            match persisted.iter().find(|d| d.name == dashboard.name) {
+                Some(saved) => {
+                    dashboard.custom_charts.clear();
+                    dashboard.sections = saved.sections.clone();
+                }
+                None => {
+                    let custom = Section {
+                        charts: dashboard.custom_charts.drain(..).map(Panel::new).collect(),
+                        ..Default::default()
+                    };
+                    dashboard.sections.insert(0, custom);
+                }
+            }
+            if dashboard
+                .sections
+                .first()
+                .is_none_or(|s| !s.title.is_empty())
+            {
+                dashboard.sections.insert(0, Section::default());
+            }
+            for section in dashboard.sections.iter_mut() {
+                for panel in section.charts.iter_mut() {
+                    panel.id = Some(next_id);
+                    next_id += 1;
+                }
+            }
+        }
+
+        Self {
+            dashboards,
+            next_id,
+            persist_path: options.persist_path.clone(),
+            persist_error,
+        }
+    }
+
+    /// Error of loading the persisted file, while the edits are refused.
+    pub fn persist_error(&self) -> Option<ConfigError> {
+        self.check_persisted().err()
+    }
+
+    pub fn dashboards(&self) -> &[Dashboard] {
+        &self.dashboards
+    }
+
+    /// Finds a dashboard by name, or returns the first one when no name is given.
+    pub fn dashboard(&self, name: Option<&str>) -> Option<&Dashboard> {
+        match name {
+            Some(name) => self.dashboards.iter().find(|d| d.name == name),
+            None => self.dashboards.first(),
+        }
+    }
+
+    /// Adds a chart to a dashboard, returning it with its identifier.
+    /// Returns `None` if there is no dashboard with this name.
+    pub fn add(
+        &mut self,
+        dashboard: Option<&str>,
+        edit: ChartEdit,
+    ) -> Result<Option<Panel>, ConfigError> {
+        let index = match dashboard {
+            Some(name) => self.dashboards.iter().position(|d| d.name == name),
+            None => (!self.dashboards.is_empty()).then_some(0),
+        };
+        let Some(index) = index else {
+            return Ok(None);
+        };
+        self.check_persisted()?;
+        let mut panel = edit.panel;
+        validate_panel(&panel).map_err(|message| ConfigError::InvalidChart {
+            chart: "chart".to_string(),
+            message,
+        })?;
+        panel.id = Some(self.next_id);
+        self.next_id += 1;
+        self.insert(
+            index,
+            panel.clone(),
+            edit.section.as_deref().unwrap_or(""),
+            edit.position,
+        );
+        self.save()?;
+        Ok(Some(panel))
+    }
+
+    /// Replaces a chart, moving it inside its dashboard when a section or position is given.
+    /// Returns `None` if there is no chart with this identifier.
+    pub fn update(&mut self, id: u64, edit: ChartEdit) -> Result<Option<Panel>, ConfigError> {
+        validate_panel(&edit.panel).map_err(|message| ConfigError::InvalidChart {
+            chart: format!("chart {id}"),
+            message,
+        })?;
+        let Some((dashboard, section, position)) = self.find(id) else {
+            return Ok(None);
+        };
+        self.check_persisted()?;
+        let panel = Panel {
+            id: Some(id),
+            ..edit.panel
+        };
+        let sections = &mut self.dashboards[dashboard].sections;
+        if edit.section.is_none() && edit.position.is_none() {
+            sections[section].charts[position] = panel.clone();
+        } else {
+            let title = match &edit.section {
+                Some(title) => title.clone(),
+                None => sections[section].title.clone(),
+            };
+            sections[section].charts.remove(position);
+            self.insert(dashboard, panel.clone(), &title, edit.position);
+        }
+        self.save()?;
+        Ok(Some(panel))
+    }
+
+    /// Removes a chart, returning `None` if there is no chart with this identifier.
+    pub fn remove(&mut self, id: u64) -> Result<Option<Panel>, ConfigError> {
+        let Some((dashboard, section, position)) = self.find(id) else {
+            return Ok(None);
+        };
+        self.check_persisted()?;
+        let panel = self.dashboards[dashboard].sections[section]
+            .charts
+            .remove(position);
+        self.save()?;
+        Ok(Some(panel))
+    }
+
+    /// Returns the indexes of the dashboard, section and position of a chart.
+    fn find(&self, id: u64) -> Option<(usize, usize, usize)> {
+        self.dashboards
+            .iter()
+            .enumerate()
+            .find_map(|(i, dashboard)| {
+                dashboard
+                    .sections
+                    .iter()
+                    .enumerate()
+                    .find_map(|(j, section)| {
+                        section
+                            .charts
+                            .iter()
+                            .position(|panel| panel.id == Some(id))
+                            .map(|k| (i, j, k))
+                    })
+            })
+    }
+
+    fn insert(&mut self, dashboard: usize, panel: Panel, title: &str, position: Option<usize>) {
+        let sections = &mut self.dashboards[dashboard].sections;
+        let section = match sections.iter().position(|s| s.title == title) {
+            Some(section) => section,
+            None => {
+                sections.push(Section {
+                    title: title.to_string(),
+                    ..Default::default()
+                });
+                sections.len() - 1
+            }
+        };
+        let charts = &mut sections[section].charts;
+        let position = position.unwrap_or(charts.len()).min(charts.len());
+        charts.insert(position, panel);
+    }
+
+    /// Refuses the edits when the persisted file failed to load, as saving would overwrite it.
+    fn check_persisted(&self) -> Result<(), ConfigError> {
+        match (&self.persist_path, &self.persist_error) {
+            (Some(path), Some(message)) => Err(ConfigError::Persisted {
+                path: path.display().to_string(),
+                message: message.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the dashboards to the persisted file, through a temporary file so a failed write
+    /// does not lose the previous charts.
+    fn save(&self) -> Result<(), ConfigError> {
+        let Some(path) = &self.persist_path else {
+            return Ok(());
+        };
+        let content = serde_json::to_string_pretty(&self.dashboards)
+            .map_err(|err| ConfigError::Io(err.into()))?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Reads the dashboards of the persisted file, none if the file does not exist yet.
+fn load_persisted(path: &Path) -> Result<Vec<Dashboard>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).map_err(|err| err.to_string()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(vec![]),
+        Err(err) => Err(err.to_string()),
+    }
+}
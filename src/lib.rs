@@ -7,16 +7,17 @@
 //!
 //! let dashboard_options = DashboardOptions {
 //!     custom_charts: vec![
-//!         ChartType::Line {
-//!             metrics: vec![
+//!         ChartType::line(
+//!             vec![
 //!                 "demo_live_time".to_string(),
 //!                 "demo_live_time_max".to_string(),
 //!             ],
-//!             desc: "Demo metric line".to_string(),
-//!             unit: "Seconds".to_string(),
-//!         },
+//!             "Demo metric line",
+//!             "Seconds",
+//!         ),
 //!     ],
 //!     include_default: true,
+//!     ..Default::default()
 //! };
 //!
 //! let app = Route::new().nest("/dashboard/", build_dashboard_route(dashboard_options));
@@ -30,21 +31,32 @@
 //! describe_counter!("demo_metric1", "Demo metric1");
 //! counter!("demo_metric1").increment(1);
 //! ```
-use std::collections::HashMap;
-use std::vec;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::{mem, vec};
 
+pub use config::ConfigError;
+pub use display::MetricDisplay;
+pub use grouping::DefaultGrouping;
+pub use layout::{ChartEdit, Dashboard, Panel, Section, MAIN_DASHBOARD};
 pub use metrics;
+use metrics::Unit;
+pub use units::{UnitInfo, UnitScale};
 
 #[cfg(feature = "system")]
 use metrics_process::register_sysinfo_event;
 use metrics_prometheus::failure::strategy::{self, NoOp};
 use metrics_util::layers::FanoutBuilder;
 pub use middleware::HttpMetricMiddleware;
+use parking_lot::RwLock;
 use poem::EndpointExt;
 use poem::{
-    handler,
-    web::{Data, Json, Query},
-    Route,
+    error::NotFoundError,
+    get, handler,
+    http::StatusCode,
+    put,
+    web::{Data, Json, Path, Query},
+    Result, Route,
 };
 
 #[cfg(not(feature = "embed"))]
@@ -55,55 +67,488 @@ use poem::endpoint::{EmbeddedFileEndpoint, EmbeddedFilesEndpoint};
 #[cfg(feature = "embed")]
 use rust_embed::RustEmbed;
 
-use recorder::{DashboardRecorder, MetricMeta, MetricValue};
+use recorder::{
+    series::{key_names, series_name, SeriesFilter},
+    Annotation, DashboardRecorder, HistogramValue, MetricMeta, MetricValue, ScatterValue,
+    TableValue,
+};
 use serde::{Deserialize, Serialize};
 
+mod config;
+mod display;
+pub mod grafana;
+mod grouping;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod layout;
+#[cfg(any(feature = "log", feature = "tracing"))]
+pub mod log_metrics;
+mod metric_filter;
 #[cfg(feature = "system")]
 pub mod metrics_process;
 mod middleware;
 pub mod recorder;
+mod text;
+#[cfg(feature = "tracing")]
+pub mod tracing_layer;
+mod units;
 
 #[cfg(feature = "embed")]
 #[derive(RustEmbed)]
 #[folder = "public"]
 pub struct Files;
 
+/// Chart groups registered by the integrations of this crate, see [`register_default_charts`].
+static DEFAULT_CHARTS: RwLock<Vec<(String, Vec<ChartType>)>> = RwLock::new(vec![]);
+
+/// Registers a named group of charts which will be shown when `include_default` is enabled.
+/// Registering the same group twice is ignored, so integrations can call it whenever they are created.
+pub fn register_default_charts(group: &str, charts: Vec<ChartType>) {
+    let mut groups = DEFAULT_CHARTS.write();
+    if groups.iter().any(|(name, _)| name == group) {
+        return;
+    }
+    groups.push((group.to_string(), charts));
+}
+
+/// Settings of the dashboards for the frontend.
+#[derive(Debug, Serialize)]
+struct DashboardsInfo {
+    editable: bool,
+    /// Error of loading the persisted charts, the edits being refused until it is fixed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    persist_error: Option<String>,
+    /// Names of the dashboards, the first one is shown by default.
+    dashboards: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DashboardQuery {
+    dashboard: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct MetricQuery {
     keys: String,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Deserialize)]
+struct HistogramQuery {
+    key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnnotationQuery {
+    /// Milliseconds since the unix epoch.
+    since: Option<u64>,
+    /// Sequence number of the last annotation seen, taking precedence over `since`.
+    after: Option<u64>,
+}
+
+/// An annotation posted to `/api/annotations`, at the current time by default.
+#[derive(Debug, Deserialize)]
+struct NewAnnotation {
+    text: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    ts: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TableQuery {
+    key: String,
+    sort: Option<String>,
+    descending: Option<bool>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScatterQuery {
+    x: String,
+    y: String,
+    #[serde(default)]
+    trend: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextQuery {
+    template: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TextValue {
+    text: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct DashboardOptions {
     /// This is custom charts that you want to show in dashboard.
     pub custom_charts: Vec<ChartType>,
     /// Whether to include metrics that not mention in the charts options.
     /// This is useful when you want to include all metrics in the dashboard.
     pub include_default: bool,
+    /// Patterns of the metrics shown in the dashboard, all metrics when empty. A pattern is
+    /// written like a series selector: `http_*` matches the metric names with a glob,
+    /// `{__name__=~"process_.*"}` with a regex, `{__type__="Histogram"}` the metric type and
+    /// `{instance="internal"}` the metrics having a series with these labels.
+    pub include_metrics: Vec<String>,
+    /// Patterns of the metrics hidden from the dashboard, like `include_metrics`.
+    /// Hidden metrics get no default chart and are left out of `/api/metrics`, but are still
+    /// exported to `/prometheus`.
+    pub exclude_metrics: Vec<String>,
+    /// Display of the metrics by name, like a name, precision or scale of their values.
+    pub metric_display: BTreeMap<String, MetricDisplay>,
+    /// How the default charts of every dashboard are grouped, one chart per metric by default.
+    pub default_grouping: DefaultGrouping,
+    /// Sections shown after the custom charts. The default charts not included in any section
+    /// are shown in a last section.
+    pub sections: Vec<Section>,
+    /// Whether the charts can be added, edited, moved and deleted from the browser,
+    /// with `POST /api/charts`, `PUT /api/charts/:id` and `DELETE /api/charts/:id`.
+    /// Annotations are posted to `POST /api/annotations` only when the charts are editable.
+    pub editable: bool,
+    /// A JSON file keeping the charts edited at runtime across restarts.
+    /// When the file exists, its charts replace the configured ones of each dashboard.
+    /// A file which fails to load is never overwritten: the configured charts are shown and
+    /// the edits are refused, with the error given by [`DashboardRecorder::persist_error`].
+    pub persist_path: Option<PathBuf>,
+    /// Dashboards shown in other tabs, after the main one of the options above named
+    /// [`MAIN_DASHBOARD`]. The main dashboard is omitted when it has no charts.
+    pub dashboards: Vec<Dashboard>,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type", content = "meta")]
 pub enum ChartType {
     Line {
         metrics: Vec<String>,
+        #[serde(default)]
         desc: String,
+        #[serde(default)]
         unit: String,
+        /// Scale and bounds of the y-axis.
+        #[serde(default)]
+        axis: Axis,
+        /// Display of the series, by metric or by series key of a selector.
+        #[serde(default)]
+        styles: BTreeMap<String, SeriesStyle>,
     },
     Bar {
         metrics: Vec<String>,
+        #[serde(default)]
+        desc: String,
+        #[serde(default)]
+        unit: String,
+        /// Scale and bounds of the y-axis.
+        #[serde(default)]
+        axis: Axis,
+        /// Display of the series, by metric or by series key of a selector.
+        #[serde(default)]
+        styles: BTreeMap<String, SeriesStyle>,
+    },
+    /// Series stacked on top of each other, for breakdowns of a total.
+    StackedArea {
+        metrics: Vec<String>,
+        #[serde(default)]
+        desc: String,
+        #[serde(default)]
+        unit: String,
+        /// Stacks the series, otherwise they overlap.
+        #[serde(default)]
+        stacked: bool,
+        /// Normalizes each point so the stacked series sum up to 100%.
+        #[serde(default)]
+        percent: bool,
+    },
+    /// Share of each metric in the sum of their current values.
+    Pie {
+        metrics: Vec<String>,
+        #[serde(default)]
+        desc: String,
+        #[serde(default)]
+        unit: String,
+        /// Draws a ring instead of a full pie.
+        #[serde(default)]
+        donut: bool,
+    },
+    /// Bucket distribution of histograms over the last minutes.
+    Histogram {
+        metrics: Vec<String>,
+        #[serde(default)]
         desc: String,
+        #[serde(default)]
         unit: String,
     },
+    /// Bucket distribution of a histogram per sampling interval, over the last minutes.
+    Heatmap {
+        metrics: Vec<String>,
+        #[serde(default)]
+        desc: String,
+        #[serde(default)]
+        unit: String,
+    },
+    /// Single big number with a sparkline, shown in the top row of the dashboard.
+    Stat {
+        metric: String,
+        #[serde(default)]
+        desc: String,
+        #[serde(default)]
+        unit: String,
+        #[serde(default)]
+        value: StatValue,
+        /// Colors the number from the greatest threshold it reaches, in the unit of the metric.
+        #[serde(default)]
+        thresholds: Vec<Threshold>,
+    },
+    /// Radial gauge of the current value between `min` and `max`.
+    /// For metrics in percent, the bounds default to 0 and 100 with amber and red segments.
+    Gauge {
+        metric: String,
+        #[serde(default)]
+        desc: String,
+        #[serde(default)]
+        unit: String,
+        #[serde(default)]
+        min: Option<f64>,
+        #[serde(default)]
+        max: Option<f64>,
+        /// Colors the gauge from the greatest threshold it reaches, in the unit of the metric.
+        #[serde(default)]
+        thresholds: Vec<Threshold>,
+    },
+    /// Table of the labeled series of a metric, with their current value, rate, minimum and
+    /// maximum over the last minutes. Only the top `limit` rows are sent by the server.
+    Table {
+        metric: String,
+        #[serde(default)]
+        desc: String,
+        #[serde(default)]
+        unit: String,
+        /// Initial sort column: `value`, `rate`, `min`, `max` or a label name.
+        #[serde(default = "default_table_sort")]
+        sort: String,
+        #[serde(default = "default_true")]
+        descending: bool,
+        #[serde(default = "default_table_limit")]
+        limit: usize,
+    },
+    /// Values of two metrics sampled in the same intervals over the last minutes, plotted one
+    /// against the other. A metric is a series, or a `rate(name)` or `quantile(0.99, name)`.
+    Scatter {
+        /// The metric on the x-axis, then the one on the y-axis.
+        metrics: [String; 2],
+        #[serde(default)]
+        desc: String,
+        #[serde(default)]
+        x_unit: String,
+        #[serde(default)]
+        y_unit: String,
+        /// Draws the line fitting the points and shows their correlation coefficient.
+        #[serde(default)]
+        trend: bool,
+    },
+    /// Markdown text like a runbook, shown next to the charts. Placeholders like
+    /// `{{queue_depth}}` are replaced by the current values of the metrics.
+    Text {
+        content: String,
+        #[serde(default)]
+        desc: String,
+    },
+}
+
+/// Value shown by a [`ChartType::Stat`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub enum StatValue {
+    /// The current value.
+    #[default]
+    Current,
+    /// The increase per second.
+    Rate,
+    /// A quantile of a histogram over the last minutes, between 0 and 1.
+    Quantile(f64),
+}
+
+/// Threshold of a [`ChartType::Stat`] or [`ChartType::Gauge`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Threshold {
+    pub value: f64,
+    /// A CSS color.
+    pub color: String,
+}
+
+/// Y-axis of a [`ChartType::Line`] or [`ChartType::Bar`], the value axis of the horizontal bars.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Axis {
+    /// Logarithmic scale, for values spanning several orders of magnitude.
+    pub log: bool,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Unit of the secondary y-axis of line charts, drawing the series styled with
+    /// `secondary` on the other side of the chart.
+    pub secondary_unit: String,
+}
+
+/// Display of a series of a [`ChartType::Line`] or [`ChartType::Bar`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct SeriesStyle {
+    /// Name shown instead of the metric.
+    pub alias: Option<String>,
+    /// A CSS color.
+    pub color: Option<String>,
+    pub line: LineStyle,
+    /// Draws the series on the secondary y-axis of a line chart.
+    pub secondary: bool,
+}
+
+/// Stroke of a series of a [`ChartType::Line`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+fn default_table_sort() -> String {
+    "value".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_table_limit() -> usize {
+    10
+}
+
+impl Threshold {
+    pub fn new(value: f64, color: &str) -> Self {
+        Self {
+            value,
+            color: color.to_string(),
+        }
+    }
 }
 
 impl ChartType {
+    /// A line chart with the default axis and series styles.
+    pub fn line(metrics: Vec<String>, desc: &str, unit: &str) -> Self {
+        ChartType::Line {
+            metrics,
+            desc: desc.to_string(),
+            unit: unit.to_string(),
+            axis: Axis::default(),
+            styles: BTreeMap::new(),
+        }
+    }
+
+    /// A bar chart with the default axis and series styles.
+    pub fn bar(metrics: Vec<String>, desc: &str, unit: &str) -> Self {
+        ChartType::Bar {
+            metrics,
+            desc: desc.to_string(),
+            unit: unit.to_string(),
+            axis: Axis::default(),
+            styles: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the y-axis of a line or bar chart, other charts are left unchanged.
+    pub fn with_axis(mut self, new_axis: Axis) -> Self {
+        if let ChartType::Line { axis, .. } | ChartType::Bar { axis, .. } = &mut self {
+            *axis = new_axis;
+        }
+        self
+    }
+
+    /// Sets the style of a metric or series key of a line or bar chart,
+    /// other charts are left unchanged.
+    pub fn with_style(mut self, key: &str, style: SeriesStyle) -> Self {
+        if let ChartType::Line { styles, .. } | ChartType::Bar { styles, .. } = &mut self {
+            styles.insert(key.to_string(), style);
+        }
+        self
+    }
+
     pub fn metrics(&self) -> &[String] {
         match self {
             ChartType::Line { metrics, .. } => metrics,
             ChartType::Bar { metrics, .. } => metrics,
+            ChartType::StackedArea { metrics, .. } => metrics,
+            ChartType::Pie { metrics, .. } => metrics,
+            ChartType::Histogram { metrics, .. } => metrics,
+            ChartType::Heatmap { metrics, .. } => metrics,
+            ChartType::Stat { metric, .. } => std::slice::from_ref(metric),
+            ChartType::Gauge { metric, .. } => std::slice::from_ref(metric),
+            ChartType::Table { metric, .. } => std::slice::from_ref(metric),
+            ChartType::Scatter { metrics, .. } => metrics,
+            ChartType::Text { .. } => &[],
         }
     }
+
+    /// Fills the options left to defaults from the metric metadata.
+    fn with_defaults(mut self, metrics: &[MetricMeta]) -> Self {
+        if let ChartType::Gauge {
+            metric,
+            unit,
+            min,
+            max,
+            thresholds,
+            ..
+        } = &mut self
+        {
+            let name = series_name(metric);
+            let meta = metrics.iter().find(|m| m.key == name);
+            if meta.and_then(|m| m.unit.as_deref()) == Some(Unit::Percent.as_canonical_label()) {
+                min.get_or_insert(0.0);
+                max.get_or_insert(100.0);
+                if thresholds.is_empty() {
+                    thresholds.push(Threshold::new(70.0, "#f5a623"));
+                    thresholds.push(Threshold::new(90.0, "#f55555"));
+                }
+                if unit.is_empty() {
+                    *unit = "%".to_string();
+                }
+            }
+        }
+        // Series of metrics with a display name are named by it, unless styled otherwise
+        if let ChartType::Line {
+            metrics: keys,
+            styles,
+            ..
+        }
+        | ChartType::Bar {
+            metrics: keys,
+            styles,
+            ..
+        } = &mut self
+        {
+            for key in keys.iter() {
+                let name = series_name(key);
+                let Some(display_name) = metrics
+                    .iter()
+                    .find(|m| m.key == name)
+                    .and_then(|m| m.display_name.as_deref())
+                else {
+                    continue;
+                };
+                // The series of a selector are named by their labels after the alias
+                let alias = match SeriesFilter::parse(key).is_some_and(|f| f.is_selector()) {
+                    true => display_name.to_string(),
+                    false => key.replacen(name, display_name, 1),
+                };
+                styles
+                    .entry(key.clone())
+                    .or_default()
+                    .alias
+                    .get_or_insert(alias);
+            }
+        }
+        self
+    }
 }
 
 #[handler]
@@ -114,37 +559,232 @@ fn prometheus_metrics(Data(recorder): Data<&metrics_prometheus::Recorder<NoOp>>)
 }
 
 #[handler]
-fn api_charts(Data(recorder): Data<&DashboardRecorder>) -> Json<Vec<ChartType>> {
+fn api_charts(
+    Data(recorder): Data<&DashboardRecorder>,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Json<Vec<Section>>> {
+    let sections = dashboard_sections(recorder, query.dashboard.as_deref()).ok_or(NotFoundError)?;
+    Ok(Json(sections))
+}
+
+/// Resolves the sections shown in a dashboard: its configured charts with their defaults,
+/// and the default charts when `include_default` is enabled.
+/// Returns `None` if there is no dashboard with this name.
+pub(crate) fn dashboard_sections(
+    recorder: &DashboardRecorder,
+    dashboard: Option<&str>,
+) -> Option<Vec<Section>> {
     let option = &recorder.options;
-    let mut res: Vec<ChartType> = vec![];
-    let mut included_metrics = HashMap::new();
-    for chart in option.custom_charts.iter() {
-        res.push(chart.clone());
-        for metric in chart.metrics() {
-            included_metrics.insert(metric.clone(), true);
+    let metas = recorder.metrics();
+    let charts = recorder.charts.read();
+    let dashboard = charts.dashboard(dashboard)?;
+    let include_default = dashboard.include_default;
+    let mut sections: Vec<Section> = dashboard
+        .sections
+        .iter()
+        .map(|section| Section {
+            charts: section
+                .charts
+                .iter()
+                .map(|panel| Panel {
+                    chart: panel.chart.clone().with_defaults(&metas),
+                    ..panel.clone()
+                })
+                .collect(),
+            ..section.clone()
+        })
+        .collect();
+    drop(charts);
+
+    if include_default {
+        let mut configured: Vec<ChartType> = sections
+            .iter()
+            .flat_map(|s| s.charts.iter())
+            .map(|panel| panel.chart.clone())
+            .collect();
+        let mut included_metrics = HashMap::new();
+        for chart in configured.iter() {
+            for name in chart.metrics().iter().flat_map(|m| key_names(m)) {
+                included_metrics.insert(name, true);
+            }
         }
-    }
-    if option.include_default {
-        let metrics = recorder.metrics();
-        for meta in metrics.iter() {
-            if included_metrics.contains_key(&meta.key) {
-                continue;
+
+        // Hidden metrics get no default chart, only known metrics can be hidden
+        let visible: HashSet<String> = recorder
+            .visible_metrics()
+            .into_iter()
+            .map(|m| m.key)
+            .collect();
+        let hidden =
+            |name: &String| metas.iter().any(|m| m.key == *name) && !visible.contains(name);
+
+        // Default charts with the group which registered them
+        let mut defaults: Vec<(Option<String>, ChartType)> = vec![];
+        for (group, charts) in DEFAULT_CHARTS.read().iter() {
+            for chart in charts {
+                if chart
+                    .metrics()
+                    .iter()
+                    .flat_map(|m| key_names(m))
+                    .any(|n| hidden(&n))
+                {
+                    continue;
+                }
+                // Skip the charts which are already configured
+                if configured.iter().any(|c| {
+                    mem::discriminant(c) == mem::discriminant(chart)
+                        && c.metrics() == chart.metrics()
+                }) {
+                    continue;
+                }
+                configured.push(chart.clone());
+                defaults.push((Some(group.clone()), chart.clone().with_defaults(&metas)));
+                for name in chart.metrics().iter().flat_map(|m| key_names(m)) {
+                    included_metrics.insert(name, true);
+                }
             }
-            let chart = ChartType::Line {
-                metrics: vec![meta.key.clone()],
-                desc: meta.desc.clone().unwrap_or_else(|| meta.key.clone()),
-                unit: meta.unit.clone().unwrap_or_else(|| "".to_string()),
-            };
-            res.push(chart.clone());
+        }
+
+        let uncovered: Vec<&MetricMeta> = metas
+            .iter()
+            .filter(|meta| !included_metrics.contains_key(&meta.key) && visible.contains(&meta.key))
+            .collect();
+        for chart in grouping::default_charts(&uncovered, option.default_grouping) {
+            defaults.push((None, chart.with_defaults(&metas)));
+        }
+
+        // Stat tiles are shown first among the default charts, as an at-a-glance row.
+        // The configured charts keep their order, which the edits refer to
+        defaults.sort_by_key(|(_, chart)| !matches!(chart, ChartType::Stat { .. }));
+
+        // Each section takes the default charts matching its patterns, in the patterns order
+        for section in sections.iter_mut() {
+            for pattern in section.include.iter() {
+                let (matched, rest) = defaults.into_iter().partition(|(group, chart)| {
+                    group.as_deref().is_some_and(|g| glob_match(pattern, g))
+                        || chart
+                            .metrics()
+                            .iter()
+                            .any(|m| glob_match(pattern, series_name(m)))
+                });
+                defaults = rest;
+                section
+                    .charts
+                    .extend(matched.into_iter().map(|(_, chart)| Panel::new(chart)));
+            }
+        }
+        // The first section is the untitled one of the custom charts
+        let rest = defaults.into_iter().map(|(_, chart)| Panel::new(chart));
+        match sections.len() {
+            1 => sections[0].charts.extend(rest),
+            _ => sections.push(Section {
+                title: "Other".to_string(),
+                charts: rest.collect(),
+                ..Default::default()
+            }),
         }
     }
 
-    Json(res)
+    // Empty sections are kept in an editable dashboard, to add charts into them
+    let res: Vec<Section> = sections
+        .into_iter()
+        .filter(|section| option.editable || !section.charts.is_empty())
+        .collect();
+    Some(res)
+}
+
+#[handler]
+fn api_grafana(
+    Data(recorder): Data<&DashboardRecorder>,
+    Query(query): Query<DashboardQuery>,
+) -> Result<Json<serde_json::Value>> {
+    let model =
+        grafana::dashboard_json(recorder, query.dashboard.as_deref()).ok_or(NotFoundError)?;
+    Ok(Json(model))
+}
+
+#[handler]
+fn api_dashboards(Data(recorder): Data<&DashboardRecorder>) -> Json<DashboardsInfo> {
+    Json(DashboardsInfo {
+        editable: recorder.options.editable,
+        persist_error: recorder.persist_error().map(|err| err.to_string()),
+        dashboards: recorder
+            .charts
+            .read()
+            .dashboards()
+            .iter()
+            .map(|d| d.name.clone())
+            .collect(),
+    })
+}
+
+#[handler]
+fn api_add_chart(
+    Data(recorder): Data<&DashboardRecorder>,
+    Query(query): Query<DashboardQuery>,
+    Json(edit): Json<ChartEdit>,
+) -> Result<Json<Panel>> {
+    let panel = recorder
+        .charts
+        .write()
+        .add(query.dashboard.as_deref(), edit)
+        .map_err(config_error_response)?
+        .ok_or(NotFoundError)?;
+    Ok(Json(panel))
+}
+
+#[handler]
+fn api_update_chart(
+    Data(recorder): Data<&DashboardRecorder>,
+    Path(id): Path<u64>,
+    Json(edit): Json<ChartEdit>,
+) -> Result<Json<Panel>> {
+    let panel = recorder
+        .charts
+        .write()
+        .update(id, edit)
+        .map_err(config_error_response)?
+        .ok_or(NotFoundError)?;
+    Ok(Json(panel))
+}
+
+#[handler]
+fn api_delete_chart(
+    Data(recorder): Data<&DashboardRecorder>,
+    Path(id): Path<u64>,
+) -> Result<Json<Panel>> {
+    let panel = recorder
+        .charts
+        .write()
+        .remove(id)
+        .map_err(config_error_response)?
+        .ok_or(NotFoundError)?;
+    Ok(Json(panel))
+}
+
+fn config_error_response(err: ConfigError) -> poem::Error {
+    let status = match err {
+        ConfigError::Io(_) | ConfigError::Persisted { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    poem::Error::from_string(err.to_string(), status)
+}
+
+/// Display of every unit, by canonical label and by name.
+#[handler]
+fn api_units() -> Json<BTreeMap<String, UnitInfo>> {
+    let mut units = BTreeMap::new();
+    for unit in units::UNITS {
+        let info = UnitInfo::from_unit(unit);
+        units.insert(unit.as_str().to_string(), info.clone());
+        units.insert(info.label.clone(), info);
+    }
+    Json(units)
 }
 
 #[handler]
 fn api_metrics(Data(recorder): Data<&DashboardRecorder>) -> Json<Vec<MetricMeta>> {
-    Json(recorder.metrics())
+    Json(recorder.visible_metrics())
 }
 
 #[handler]
@@ -156,6 +796,87 @@ fn api_metrics_value(
     Json(recorder.metrics_value(keys))
 }
 
+#[handler]
+fn api_histogram(
+    Data(recorder): Data<&DashboardRecorder>,
+    Query(query): Query<HistogramQuery>,
+) -> Result<Json<HistogramValue>> {
+    let value = recorder.histogram_value(&query.key).ok_or(NotFoundError)?;
+    Ok(Json(value))
+}
+
+#[handler]
+fn api_annotations(
+    Data(recorder): Data<&DashboardRecorder>,
+    Query(query): Query<AnnotationQuery>,
+) -> Json<Vec<Annotation>> {
+    Json(match query.after {
+        Some(id) => recorder.annotations_after(id),
+        None => recorder.annotations(query.since),
+    })
+}
+
+#[handler]
+fn api_add_annotation(
+    Data(recorder): Data<&DashboardRecorder>,
+    Json(body): Json<NewAnnotation>,
+) -> Result<Json<Annotation>> {
+    if body.text.trim().is_empty() {
+        return Err(poem::Error::from_string(
+            "annotation has no text",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    let annotation = match body.ts {
+        Some(ts) => recorder.add_annotation(Annotation {
+            id: 0,
+            ts,
+            text: body.text,
+            tags: body.tags,
+        }),
+        None => recorder.annotate(body.text, body.tags),
+    };
+    Ok(Json(annotation))
+}
+
+#[handler]
+fn api_table(
+    Data(recorder): Data<&DashboardRecorder>,
+    Query(query): Query<TableQuery>,
+) -> Result<Json<TableValue>> {
+    let value = recorder
+        .table_value(
+            &query.key,
+            query.sort.as_deref().unwrap_or("value"),
+            query.descending.unwrap_or(true),
+            query.limit.unwrap_or(10),
+        )
+        .ok_or(NotFoundError)?;
+    Ok(Json(value))
+}
+
+#[handler]
+fn api_scatter(
+    Data(recorder): Data<&DashboardRecorder>,
+    Query(query): Query<ScatterQuery>,
+) -> Result<Json<ScatterValue>> {
+    let value = recorder
+        .scatter_value(&query.x, &query.y, query.trend)
+        .ok_or(NotFoundError)?;
+    Ok(Json(value))
+}
+
+/// Markdown of a text panel, with its placeholders filled from the current values.
+#[handler]
+fn api_text(
+    Data(recorder): Data<&DashboardRecorder>,
+    Query(query): Query<TextQuery>,
+) -> Json<TextValue> {
+    Json(TextValue {
+        text: text::render(recorder, &query.template),
+    })
+}
+
 pub fn build_dashboard_route(opts: DashboardOptions) -> Route {
     build_dashboard_route_with_recorder(opts).1
 }
@@ -175,15 +896,51 @@ pub fn build_dashboard_route_with_recorder(opts: DashboardOptions) -> (Dashboard
     metrics::set_global_recorder(recoder_fanout).expect("Should register a recorder successfull");
     #[cfg(feature = "system")]
     register_sysinfo_event();
+    #[cfg(feature = "system")]
+    register_default_charts(
+        "system",
+        vec![ChartType::Stat {
+            metric: metrics_process::PROCESS_MEMORY_USAGE.to_string(),
+            desc: "Process memory".to_string(),
+            unit: Unit::Bytes.as_canonical_label().to_string(),
+            value: StatValue::Current,
+            thresholds: vec![],
+        }],
+    );
 
+    let charts = match recorder2.options.editable {
+        true => get(api_charts).post(api_add_chart),
+        false => get(api_charts),
+    };
+    let annotations = match recorder2.options.editable {
+        true => get(api_annotations).post(api_add_annotation),
+        false => get(api_annotations),
+    };
     let route = Route::new()
         .at("/prometheus", prometheus_metrics.data(recorder1))
         .at("/api/metrics", api_metrics.data(recorder2.clone()))
-        .at("/api/charts", api_charts.data(recorder2.clone()))
+        .at("/api/units", get(api_units))
+        .at("/api/dashboards", api_dashboards.data(recorder2.clone()))
+        .at("/api/charts", charts.data(recorder2.clone()))
         .at(
             "/api/metrics_value",
             api_metrics_value.data(recorder2.clone()),
-        );
+        )
+        .at("/api/histogram", api_histogram.data(recorder2.clone()))
+        .at("/api/table", api_table.data(recorder2.clone()))
+        .at("/api/scatter", api_scatter.data(recorder2.clone()))
+        .at("/api/text", api_text.data(recorder2.clone()))
+        .at("/api/annotations", annotations.data(recorder2.clone()))
+        .at("/api/grafana.json", api_grafana.data(recorder2.clone()));
+    let route = match recorder2.options.editable {
+        true => route.at(
+            "/api/charts/:id",
+            put(api_update_chart)
+                .delete(api_delete_chart)
+                .data(recorder2.clone()),
+        ),
+        false => route,
+    };
 
     #[cfg(not(feature = "embed"))]
     let route = route.nest(
@@ -199,7 +956,26 @@ pub fn build_dashboard_route_with_recorder(opts: DashboardOptions) -> (Dashboard
     (recorder2, route)
 }
 
-#[allow(unused)]
-pub(crate) fn round_up_f64_2digits(input: f64) -> f64 {
-    (input * 100.0).round() / 100.0
+/// Matches `text` against a glob `pattern`, where `*` matches any characters and `?` one character.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            backtrack = Some((star_p, star_t + 1));
+            p = star_p + 1;
+            t = star_t + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
 }
@@ -0,0 +1,136 @@
+//! Counts log events by level and target, either as a `log::Log` wrapper or as a tracing layer:
+//!
+//! ```rust,ignore
+//! // with `log`
+//! log::set_boxed_logger(Box::new(LogMetrics::new(env_logger::Logger::from_default_env())))?;
+//!
+//! // with `tracing`
+//! tracing_subscriber::registry()
+//!     .with(tracing_subscriber::fmt::layer())
+//!     .with(LogMetricsLayer::new())
+//!     .init();
+//! ```
+//!
+//! Both record into `log_events_total` with the `level` and `target` labels and register the
+//! "Log events" chart. When `log` records are forwarded into `tracing`, only one of them should
+//! be installed to avoid counting the events twice. The `MetricsLayer` of span metrics in
+//! `tracing_layer` counts the events too only when enabled with `with_event_counts`.
+use std::sync::Once;
+
+use metrics::{counter, describe_counter, Unit};
+
+use crate::{register_default_charts, ChartType};
+
+pub const LOG_EVENTS_TOTAL: &str = "log_events_total";
+
+const LEVELS: [&str; 4] = ["error", "warn", "info", "debug"];
+
+static DESCRIBE: Once = Once::new();
+
+pub(crate) fn register_log_events() {
+    register_default_charts(
+        "log",
+        vec![ChartType::line(
+            LEVELS
+                .iter()
+                .map(|level| format!("{LOG_EVENTS_TOTAL}{{level=\"{level}\"}}"))
+                .collect(),
+            "Log events",
+            Unit::Count.as_canonical_label(),
+        )],
+    );
+}
+
+/// Counts an event, `level` being the uppercase name of a `log` or `tracing` level.
+pub(crate) fn record_log_event(level: &str, target: &str) {
+    DESCRIBE.call_once(|| {
+        describe_counter!(LOG_EVENTS_TOTAL, Unit::Count, "Log events");
+    });
+    counter!(LOG_EVENTS_TOTAL, "level" => level_label(level), "target" => target.to_string())
+        .increment(1);
+}
+
+fn level_label(level: &str) -> &'static str {
+    match level {
+        "ERROR" => "error",
+        "WARN" => "warn",
+        "INFO" => "info",
+        "DEBUG" => "debug",
+        _ => "trace",
+    }
+}
+
+#[cfg(feature = "log")]
+pub use self::log_wrapper::LogMetrics;
+
+#[cfg(feature = "log")]
+mod log_wrapper {
+    use log::{Log, Metadata, Record};
+
+    use super::{record_log_event, register_log_events};
+
+    /// [`Log`] implementation which counts the records then forwards them to the inner logger.
+    pub struct LogMetrics<L> {
+        inner: L,
+    }
+
+    impl<L: Log> LogMetrics<L> {
+        pub fn new(inner: L) -> Self {
+            register_log_events();
+            Self { inner }
+        }
+    }
+
+    impl<L: Log> Log for LogMetrics<L> {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            self.inner.enabled(metadata)
+        }
+
+        fn log(&self, record: &Record) {
+            if self.inner.enabled(record.metadata()) {
+                record_log_event(record.level().as_str(), record.target());
+            }
+            self.inner.log(record)
+        }
+
+        fn flush(&self) {
+            self.inner.flush()
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+pub use self::tracing_events::LogMetricsLayer;
+
+#[cfg(feature = "tracing")]
+mod tracing_events {
+    use tracing_core::{Event, Subscriber};
+    use tracing_subscriber::{layer::Context, Layer};
+
+    use super::{record_log_event, register_log_events};
+
+    /// Layer which counts the tracing events.
+    pub struct LogMetricsLayer {
+        _priv: (),
+    }
+
+    impl LogMetricsLayer {
+        pub fn new() -> Self {
+            register_log_events();
+            Self { _priv: () }
+        }
+    }
+
+    impl Default for LogMetricsLayer {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for LogMetricsLayer {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let meta = event.metadata();
+            record_log_event(meta.level().as_str(), meta.target());
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Patterns of `include_metrics` and `exclude_metrics`, deciding which metrics the dashboard
+//! shows. Hidden metrics are still exported to `/prometheus`.
+use crate::{
+    glob_match,
+    recorder::{
+        series::{Labels, SeriesFilter},
+        MetricMeta,
+    },
+};
+
+/// Pseudo label matched against the metric name.
+const NAME_LABEL: &str = "__name__";
+/// Pseudo label matched against the metric type: `Counter`, `Gauge` or `Histogram`.
+const TYPE_LABEL: &str = "__type__";
+
+/// A pattern matching metrics, written like a series selector: `http_*` matches the names
+/// with a glob, `{__name__=~"process_.*"}` with a regex, `{__type__="Histogram"}` the type
+/// and `{instance="internal"}` the metrics having a series with these labels.
+pub(crate) struct MetricPattern {
+    filter: SeriesFilter,
+}
+
+impl MetricPattern {
+    /// Returns `None` if the pattern is not a valid selector.
+    pub fn parse(input: &str) -> Option<Self> {
+        SeriesFilter::parse(input).map(|filter| Self { filter })
+    }
+
+    /// Whether the pattern matches a metric whose series have the given labels.
+    pub fn matches(&self, meta: &MetricMeta, series: &[&Labels]) -> bool {
+        if !self.filter.name.is_empty() && !glob_match(&self.filter.name, &meta.key) {
+            return false;
+        }
+        let (pseudo, labels): (Vec<_>, Vec<_>) = self
+            .filter
+            .matchers
+            .iter()
+            .partition(|m| m.label == NAME_LABEL || m.label == TYPE_LABEL);
+        let pseudo = pseudo.iter().all(|m| match m.label.as_str() {
+            NAME_LABEL => m.matches(&meta.key),
+            _ => m.matches(meta.typ.as_str()),
+        });
+        // A metric without series yet has only missing labels
+        let no_labels = Labels::new();
+        let matches_series = |series: &Labels| {
+            labels.iter().all(|m| {
+                let value = series.iter().find(|(label, _)| *label == m.label);
+                m.matches(value.map(|(_, v)| v.as_str()).unwrap_or_default())
+            })
+        };
+        pseudo
+            && match series.is_empty() {
+                true => matches_series(&no_labels),
+                false => series.iter().any(|s| matches_series(s)),
+            }
+    }
+}
+
+/// The patterns of the options: a metric is shown when it matches an `include` pattern, or
+/// when there are none, and no `exclude` pattern.
+pub(crate) struct MetricVisibility {
+    include: Vec<MetricPattern>,
+    exclude: Vec<MetricPattern>,
+}
+
+impl MetricVisibility {
+    /// Invalid patterns are ignored, they are reported by the validation of the options.
+    pub fn new(include: &[String], exclude: &[String]) -> Self {
+        let parse = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter_map(|p| MetricPattern::parse(p))
+                .collect()
+        };
+        Self {
+            include: parse(include),
+            exclude: parse(exclude),
+        }
+    }
+
+    pub fn is_visible(&self, meta: &MetricMeta, series: &[&Labels]) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(meta, series)))
+            && !self.exclude.iter().any(|p| p.matches(meta, series))
+    }
+}
@@ -4,8 +4,6 @@ use std::time::Duration;
 
 use sysinfo::{get_current_pid, Disks, Networks, System};
 
-use crate::round_up_f64_2digits;
-
 const REFRESH_INTERVAL_SECONDS: u64 = 2;
 
 pub const SYSTEM_CPU_CORE: &str = "system_cpu_core";
@@ -82,16 +80,13 @@ pub fn register_sysinfo_event() {
             for cpu in sys.cpus() {
                 sum += cpu.cpu_usage() as f64;
             }
-            gauge!(SYSTEM_CPU_USAGE).set(round_up_f64_2digits(sum / sys.cpus().len() as f64));
+            gauge!(SYSTEM_CPU_USAGE).set(sum / sys.cpus().len() as f64);
 
             gauge!(SYSTEM_MEMORY_TOTAL).set(sys.total_memory() as f64);
-            gauge!(SYSTEM_MEMORY_USAGE).set(round_up_f64_2digits(
-                100.0 * sys.used_memory() as f64 / sys.total_memory() as f64,
-            ));
+            gauge!(SYSTEM_MEMORY_USAGE)
+                .set(100.0 * sys.used_memory() as f64 / sys.total_memory() as f64);
             gauge!(SYSTEM_SWAP_TOTAL).set(sys.total_swap() as f64);
-            gauge!(SYSTEM_SWAP_USAGE).set(round_up_f64_2digits(
-                100.0 * sys.used_swap() as f64 / sys.total_swap() as f64,
-            ));
+            gauge!(SYSTEM_SWAP_USAGE).set(100.0 * sys.used_swap() as f64 / sys.total_swap() as f64);
 
             let mut disk_used = 0.0;
             let mut disk_sum = 0.0;
@@ -100,7 +95,7 @@ pub fn register_sysinfo_event() {
                 disk_used += (disk.total_space() - disk.available_space()) as f64;
             }
 
-            gauge!(SYSTEM_DISK_USAGE).set(round_up_f64_2digits(100.0 * disk_used / disk_sum));
+            gauge!(SYSTEM_DISK_USAGE).set(100.0 * disk_used / disk_sum);
 
             let mut up_sum = 0;
             let mut down_sum = 0;
@@ -110,15 +105,14 @@ pub fn register_sysinfo_event() {
             }
 
             if up_sum >= network_up_pre {
-                gauge!(SYSTEM_NETWORK_UP_SPEED).set(round_up_f64_2digits(
-                    8.0 * (up_sum - network_up_pre) as f64 / REFRESH_INTERVAL_SECONDS as f64,
-                ));
+                gauge!(SYSTEM_NETWORK_UP_SPEED)
+                    .set(8.0 * (up_sum - network_up_pre) as f64 / REFRESH_INTERVAL_SECONDS as f64);
             }
 
             if down_sum >= network_down_pre {
-                gauge!(SYSTEM_NETWORK_DOWN_SPEED).set(round_up_f64_2digits(
+                gauge!(SYSTEM_NETWORK_DOWN_SPEED).set(
                     8.0 * (down_sum - network_down_pre) as f64 / REFRESH_INTERVAL_SECONDS as f64,
-                ));
+                );
             }
 
             gauge!(SYSTEM_NETWORK_UP_COUNT).set(up_sum as f64);
@@ -129,7 +123,7 @@ pub fn register_sysinfo_event() {
 
             // Process info
             if let Some(process) = sys.process(pid) {
-                gauge!(PROCESS_CPU_USAGE).set(round_up_f64_2digits(process.cpu_usage() as f64));
+                gauge!(PROCESS_CPU_USAGE).set(process.cpu_usage() as f64);
                 gauge!(PROCESS_MEMORY_USAGE).set(process.memory() as f64);
             }
 
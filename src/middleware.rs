@@ -1,43 +1,345 @@
-use std::time::Instant;
+use std::{
+    sync::{Arc, Once},
+    time::Instant,
+};
 
-use poem::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use http_body::Body as _;
+use http_body_util::combinators::BoxBody;
+use metrics::{
+    counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Gauge, Unit,
+};
+use poem::{
+    http::{Method, StatusCode},
+    Body, Endpoint, IntoResponse, Middleware, PathPattern, Request, Response, Result,
+};
 
-/// Middleware for [`tracing`](https://crates.io/crates/tracing).
-#[derive(Default)]
-pub struct HttpMetricMiddleware;
+use crate::{glob_match, register_default_charts, ChartType, StatValue, Threshold};
+
+const DEFAULT_PREFIX: &str = "http";
+/// Route label of the requests which did not match any route.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Middleware recording the requests count, errors count, duration, in-flight requests
+/// and body sizes of a poem endpoint.
+///
+/// The metrics are named `{prefix}_requests_total`, `{prefix}_requests_error`,
+/// `{prefix}_requests_duration_seconds`, `{prefix}_requests_in_flight`,
+/// `{prefix}_request_size_bytes` and `{prefix}_response_size_bytes`, without labels by default.
+/// The requests failed with an error are counted in `{prefix}_requests_error` only,
+/// `{prefix}_requests_total` counting the responses.
+/// The body sizes are recorded only when they are known before streaming the body,
+/// the in-flight gauge is only labeled by method as the other labels are not known yet:
+///
+/// ```rust
+/// use metrics_dashboard::HttpMetricMiddleware;
+///
+/// let middleware = HttpMetricMiddleware::new()
+///     .with_method_label(true)
+///     .with_route_label(true)
+///     .with_status_class_label(true)
+///     .exclude_path("/dashboard/*");
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpMetricMiddleware {
+    prefix: String,
+    method_label: bool,
+    route_label: bool,
+    status_class_label: bool,
+    status_label: bool,
+    exclude_paths: Vec<String>,
+}
+
+impl HttpMetricMiddleware {
+    pub fn new() -> Self {
+        Self {
+            prefix: DEFAULT_PREFIX.to_string(),
+            method_label: false,
+            route_label: false,
+            status_class_label: false,
+            status_label: false,
+            exclude_paths: vec![],
+        }
+    }
+
+    /// Sets the prefix of the metric names, default is `http`.
+    /// The default charts of another prefix are registered as the `http:{prefix}` group.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Adds the request method as the `method` label.
+    pub fn with_method_label(mut self, enabled: bool) -> Self {
+        self.method_label = enabled;
+        self
+    }
+
+    /// Adds the matched route template, like `/users/:id`, as the `route` label.
+    /// Requests which did not match any route are labeled as `unmatched`.
+    pub fn with_route_label(mut self, enabled: bool) -> Self {
+        self.route_label = enabled;
+        self
+    }
+
+    /// Adds the status code class, like `2xx`, as the `status_class` label.
+    pub fn with_status_class_label(mut self, enabled: bool) -> Self {
+        self.status_class_label = enabled;
+        self
+    }
+
+    /// Adds the exact status code as the `status` label.
+    pub fn with_status_label(mut self, enabled: bool) -> Self {
+        self.status_label = enabled;
+        self
+    }
+
+    /// Skips the requests whose path matches the pattern, where `*` matches any characters.
+    pub fn exclude_path(mut self, pattern: &str) -> Self {
+        self.exclude_paths.push(pattern.to_string());
+        self
+    }
+}
+
+impl Default for HttpMetricMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl<E: Endpoint> Middleware<E> for HttpMetricMiddleware {
     type Output = HttpMetricMiddlewareEndpoint<E>;
 
     fn transform(&self, ep: E) -> Self::Output {
-        HttpMetricMiddlewareEndpoint { inner: ep }
+        let config = HttpMetricConfig {
+            requests_total: format!("{}_requests_total", self.prefix),
+            requests_error: format!("{}_requests_error", self.prefix),
+            requests_duration: format!("{}_requests_duration_seconds", self.prefix),
+            requests_in_flight: format!("{}_requests_in_flight", self.prefix),
+            request_size: format!("{}_request_size_bytes", self.prefix),
+            response_size: format!("{}_response_size_bytes", self.prefix),
+            options: self.clone(),
+            described: Once::new(),
+        };
+        config.register_charts();
+        HttpMetricMiddlewareEndpoint {
+            inner: ep,
+            config: Arc::new(config),
+        }
     }
 }
 
-/// Endpoint for `Tracing` middleware.
+struct HttpMetricConfig {
+    requests_total: String,
+    requests_error: String,
+    requests_duration: String,
+    requests_in_flight: String,
+    request_size: String,
+    response_size: String,
+    options: HttpMetricMiddleware,
+    described: Once,
+}
+
+impl HttpMetricConfig {
+    fn describe(&self) {
+        self.described.call_once(|| {
+            describe_counter!(self.requests_total.clone(), Unit::Count, "Http requests");
+            describe_counter!(
+                self.requests_error.clone(),
+                Unit::Count,
+                "Http requests failed with an error"
+            );
+            describe_histogram!(
+                self.requests_duration.clone(),
+                Unit::Seconds,
+                "Http requests duration"
+            );
+            describe_gauge!(
+                self.requests_in_flight.clone(),
+                Unit::Count,
+                "Http requests in flight"
+            );
+            describe_histogram!(
+                self.request_size.clone(),
+                Unit::Bytes,
+                "Http request body size"
+            );
+            describe_histogram!(
+                self.response_size.clone(),
+                Unit::Bytes,
+                "Http response body size"
+            );
+        });
+    }
+
+    /// Group of the default charts, `http` for the default prefix and `http:{prefix}` otherwise,
+    /// so a prefix like `grpc` does not replace the charts of another integration.
+    fn chart_group(&self) -> String {
+        match self.options.prefix.as_str() {
+            DEFAULT_PREFIX => DEFAULT_PREFIX.to_string(),
+            prefix => format!("{DEFAULT_PREFIX}:{prefix}"),
+        }
+    }
+
+    fn register_charts(&self) {
+        register_default_charts(
+            &self.chart_group(),
+            vec![
+                ChartType::Stat {
+                    metric: self.requests_error.clone(),
+                    desc: "Http error rate".to_string(),
+                    unit: "/s".to_string(),
+                    value: StatValue::Rate,
+                    thresholds: vec![
+                        Threshold::new(1.0, "#f5a623"),
+                        Threshold::new(10.0, "#f55555"),
+                    ],
+                },
+                ChartType::Stat {
+                    metric: self.requests_duration.clone(),
+                    desc: "Http p99 latency".to_string(),
+                    unit: Unit::Seconds.as_canonical_label().to_string(),
+                    value: StatValue::Quantile(0.99),
+                    thresholds: vec![
+                        Threshold::new(0.5, "#f5a623"),
+                        Threshold::new(1.0, "#f55555"),
+                    ],
+                },
+                ChartType::line(
+                    vec![self.requests_total.clone(), self.requests_error.clone()],
+                    "Http requests",
+                    Unit::Count.as_canonical_label(),
+                ),
+                ChartType::line(
+                    vec![self.requests_in_flight.clone()],
+                    "Http requests in flight",
+                    Unit::Count.as_canonical_label(),
+                ),
+                ChartType::Heatmap {
+                    metrics: vec![self.requests_duration.clone()],
+                    desc: "Http requests duration".to_string(),
+                    unit: Unit::Seconds.as_canonical_label().to_string(),
+                },
+                ChartType::Histogram {
+                    metrics: vec![self.request_size.clone(), self.response_size.clone()],
+                    desc: "Http body size".to_string(),
+                    unit: Unit::Bytes.as_canonical_label().to_string(),
+                },
+            ],
+        );
+    }
+
+    fn is_excluded(&self, path: &str) -> bool {
+        self.options
+            .exclude_paths
+            .iter()
+            .any(|pattern| glob_match(pattern, path))
+    }
+
+    fn labels(
+        &self,
+        method: &Method,
+        pattern: Option<&PathPattern>,
+        status: StatusCode,
+    ) -> Vec<(&'static str, String)> {
+        let mut labels = vec![];
+        if self.options.method_label {
+            labels.push(("method", method.as_str().to_string()));
+        }
+        if self.options.route_label {
+            let route = pattern.map_or(UNMATCHED_ROUTE, |p| &p.0);
+            labels.push(("route", route.to_string()));
+        }
+        if self.options.status_class_label {
+            labels.push(("status_class", format!("{}xx", status.as_u16() / 100)));
+        }
+        if self.options.status_label {
+            labels.push(("status", status.as_str().to_string()));
+        }
+        labels
+    }
+}
+
+/// Endpoint for `HttpMetricMiddleware`.
 pub struct HttpMetricMiddlewareEndpoint<E> {
     inner: E,
+    config: Arc<HttpMetricConfig>,
 }
 
 impl<E: Endpoint> Endpoint for HttpMetricMiddlewareEndpoint<E> {
     type Output = Response;
 
     async fn call(&self, req: Request) -> Result<Self::Output> {
+        let config = &self.config;
+        if config.is_excluded(req.uri().path()) {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+        config.describe();
+
+        let method = req.method().clone();
+        let in_flight_labels = match config.options.method_label {
+            true => vec![("method", method.as_str().to_string())],
+            false => vec![],
+        };
+        // Decremented on drop, so errors and cancelled requests are also accounted
+        let _in_flight =
+            InFlightGuard::new(gauge!(config.requests_in_flight.clone(), &in_flight_labels));
+
+        let mut req = req;
+        let (body, request_size) = body_size(req.take_body());
+        req.set_body(body);
+
         let now = Instant::now();
         let res = self.inner.call(req).await;
         let latency = now.elapsed();
 
         match res {
             Ok(resp) => {
-                let resp = resp.into_response();
-                metrics::counter!("http_requests_total").increment(1);
-                metrics::histogram!("http_requests_duration_seconds").record(latency.as_secs_f64());
+                let mut resp = resp.into_response();
+                let labels = config.labels(&method, resp.data::<PathPattern>(), resp.status());
+                let (body, response_size) = body_size(resp.take_body());
+                resp.set_body(body);
+
+                counter!(config.requests_total.clone(), &labels).increment(1);
+                histogram!(config.requests_duration.clone(), &labels).record(latency.as_secs_f64());
+                if let Some(size) = request_size {
+                    histogram!(config.request_size.clone(), &labels).record(size as f64);
+                }
+                if let Some(size) = response_size {
+                    histogram!(config.response_size.clone(), &labels).record(size as f64);
+                }
                 Ok(resp)
             }
             Err(err) => {
-                metrics::counter!("http_requests_error").increment(1);
+                let labels = config.labels(&method, err.data::<PathPattern>(), err.status());
+                counter!(config.requests_error.clone(), &labels).increment(1);
+                histogram!(config.requests_duration.clone(), &labels).record(latency.as_secs_f64());
+                if let Some(size) = request_size {
+                    histogram!(config.request_size.clone(), &labels).record(size as f64);
+                }
                 Err(err)
             }
         }
     }
 }
+
+struct InFlightGuard(Gauge);
+
+impl InFlightGuard {
+    fn new(gauge: Gauge) -> Self {
+        gauge.increment(1.0);
+        Self(gauge)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.decrement(1.0);
+    }
+}
+
+/// Returns the body with its exact size, if known without reading it.
+fn body_size(body: Body) -> (Body, Option<u64>) {
+    let body: BoxBody<_, _> = body.into();
+    let size = body.size_hint().exact();
+    (body.into(), size)
+}
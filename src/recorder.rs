@@ -1,15 +1,40 @@
 use metrics::{Key, Metadata, Recorder};
 use parking_lot::RwLock;
 use serde::Serialize;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
-use crate::DashboardOptions;
+use crate::{
+    layout::ChartSet, metric_filter::MetricVisibility, ConfigError, DashboardOptions, MetricDisplay,
+};
 
-use self::{counter::SimpleCounter, gauge::SimpleGauge, histogram::SimpleHistogram};
+use self::{
+    annotation::AnnotationLog,
+    counter::SimpleCounter,
+    gauge::SimpleGauge,
+    histogram::{bucket_quantile, SimpleHistogram, BUCKET_BOUNDS, SAMPLE_INTERVAL_MS},
+    history::{now_ms, SeriesHistory},
+    series::{key_labels, series_key, Labels, SeriesFilter, ValueKey},
+};
 
+mod annotation;
 mod counter;
 mod gauge;
 mod histogram;
+mod history;
+mod scatter;
+pub(crate) mod series;
+
+pub use self::{
+    annotation::Annotation,
+    histogram::HistogramSample,
+    scatter::{ScatterValue, TrendLine},
+};
 
 #[derive(Debug, Serialize, Clone)]
 pub enum MetricType {
@@ -18,12 +43,30 @@ pub enum MetricType {
     Histogram,
 }
 
+impl MetricType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricType::Counter => "Counter",
+            MetricType::Gauge => "Gauge",
+            MetricType::Histogram => "Histogram",
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct MetricMeta {
     pub key: String,
-    typ: MetricType,
+    pub typ: MetricType,
     pub desc: Option<String>,
     pub unit: Option<String>,
+    /// Target of the code which registered the metric, its module path by default.
+    pub target: Option<String>,
+    /// Name shown instead of the key, from the display options.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// Decimal places of the values, from the display options.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precision: Option<u8>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -33,55 +76,399 @@ pub struct MetricValue {
     pub value_u64: Option<u64>,
     #[serde(rename = "value", skip_serializing_if = "Option::is_none")]
     pub value_f64: Option<f64>,
+    /// Values of each series selected by a selector key, sorted by series key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<Vec<MetricValue>>,
+}
+
+/// A labeled series of a metric.
+struct Series<T> {
+    labels: Labels,
+    handle: T,
+    history: SeriesHistory,
+}
+
+/// Series of each metric, by metric name then by series key.
+type SeriesMap<T> = HashMap<String, HashMap<String, Series<T>>>;
+
+/// Bucket counts of a histogram per sampling interval, over the last minutes.
+#[derive(Debug, Serialize, Clone)]
+pub struct HistogramValue {
+    pub key: String,
+    /// Upper bounds of the buckets, `None` for the last bucket which has no bound.
+    /// Only the range of buckets containing values is returned.
+    pub bounds: Vec<Option<f64>>,
+    pub interval_ms: u64,
+    /// Samples with the counts of each bucket in `bounds`, oldest first.
+    pub samples: Vec<HistogramSample>,
+}
+
+/// A labeled series with its statistics over the last minutes.
+#[derive(Debug, Serialize, Clone)]
+pub struct SeriesRow {
+    pub labels: BTreeMap<String, String>,
+    /// Current value, or values count for histograms.
+    pub value: f64,
+    /// Change per second of the value.
+    pub rate: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// The series of a metric as table rows, sorted and limited to the top rows.
+#[derive(Debug, Serialize, Clone)]
+pub struct TableValue {
+    pub key: String,
+    /// Names of the labels found in the series.
+    pub labels: Vec<String>,
+    /// Number of series matching the key, before limiting the rows.
+    pub total: usize,
+    pub rows: Vec<SeriesRow>,
 }
 
 #[derive(Default)]
 struct DashboardStorage {
-    counters: HashMap<String, SimpleCounter>,
-    gauges: HashMap<String, SimpleGauge>,
-    histograms: HashMap<String, SimpleHistogram>,
+    counters: SeriesMap<SimpleCounter>,
+    gauges: SeriesMap<SimpleGauge>,
+    histograms: SeriesMap<SimpleHistogram>,
 }
 
 impl DashboardStorage {
-    fn get_counter(&mut self, key: &str) -> SimpleCounter {
-        let entry = self.counters.entry(key.to_string()).or_default();
-        entry.clone()
+    fn get_counter(&mut self, key: &Key) -> SimpleCounter {
+        get_series(&mut self.counters, key)
+    }
+
+    fn get_gauge(&mut self, key: &Key) -> SimpleGauge {
+        get_series(&mut self.gauges, key)
+    }
+
+    fn get_histogram(&mut self, key: &Key) -> SimpleHistogram {
+        get_series(&mut self.histograms, key)
+    }
+
+    /// Appends the current value of every series to its history.
+    fn sample(&mut self, ts: u64) {
+        sample_series(&mut self.counters, ts, |c| c.value() as f64);
+        sample_series(&mut self.gauges, ts, |g| g.value());
+        sample_series(&mut self.histograms, ts, |h| h.count() as f64);
+    }
+}
+
+fn sample_series<T>(map: &mut SeriesMap<T>, ts: u64, value: impl Fn(&T) -> f64) {
+    for series in map.values_mut().flat_map(|series| series.values_mut()) {
+        series.history.push(ts, value(&series.handle));
+    }
+}
+
+/// Builds the table rows of the series selected by the filter.
+/// The current value is counted as the last sample of the history.
+fn series_rows<T>(
+    map: &SeriesMap<T>,
+    filter: &SeriesFilter,
+    value: impl Fn(&T) -> f64,
+) -> Vec<SeriesRow> {
+    let now = now_ms();
+    map.get(&filter.name)
+        .into_iter()
+        .flat_map(|series| series.values())
+        .filter(|s| filter.matches(&s.labels))
+        .map(|s| {
+            let current = value(&s.handle);
+            let mut history = s.history.clone();
+            history.push(now, current);
+            SeriesRow {
+                labels: s.labels.iter().cloned().collect(),
+                value: current,
+                rate: history.rate(),
+                min: history.min(),
+                max: history.max(),
+            }
+        })
+        .collect()
+}
+
+/// Sums a statistic of the series selected by the filter, computed from the history and
+/// the current value of each series.
+fn series_stat<T>(
+    map: &SeriesMap<T>,
+    filter: &SeriesFilter,
+    value: impl Fn(&T) -> f64,
+    stat: impl Fn(&SeriesHistory, f64) -> Option<f64>,
+) -> Option<f64> {
+    map.get(&filter.name)
+        .into_iter()
+        .flat_map(|series| series.values())
+        .filter(|s| filter.matches(&s.labels))
+        .filter_map(|s| stat(&s.history, value(&s.handle)))
+        .reduce(|a, b| a + b)
+}
+
+/// Computes the value of a function key like `rate(name)` from the history of the series.
+fn derived_value(
+    storage: &DashboardStorage,
+    metrics: &HashMap<String, MetricMeta>,
+    key: &ValueKey,
+) -> Option<f64> {
+    let now = now_ms();
+    let stat =
+        |filter: &SeriesFilter, stat: &dyn Fn(&SeriesHistory, f64) -> Option<f64>| match metrics
+            .get(&filter.name)?
+            .typ
+        {
+            MetricType::Counter => {
+                series_stat(&storage.counters, filter, |c| c.value() as f64, stat)
+            }
+            MetricType::Gauge => series_stat(&storage.gauges, filter, |g| g.value(), stat),
+            MetricType::Histogram => {
+                series_stat(&storage.histograms, filter, |h| h.count() as f64, stat)
+            }
+        };
+    let rate = |history: &SeriesHistory, current: f64| {
+        history.recent_rate(now, current, SAMPLE_INTERVAL_MS)
+    };
+    // Ratios and shares compare the current values of gauges, but the recent rates of the
+    // cumulative counters and histogram counts, so they follow spikes
+    let current = |filter: &SeriesFilter| match metrics.get(&filter.name)?.typ {
+        MetricType::Gauge => stat(filter, &|_, current| Some(current)),
+        MetricType::Counter | MetricType::Histogram => stat(filter, &rate),
+    };
+    match key {
+        ValueKey::Rate(filter) => stat(filter, &rate),
+        ValueKey::Ratio(a, b) => {
+            let (a, b) = (current(a)?, current(b)?);
+            (b != 0.0).then(|| a / b)
+        }
+        ValueKey::Percent(filter) => {
+            let all = SeriesFilter {
+                name: filter.name.clone(),
+                matchers: vec![],
+            };
+            let total = current(&all)?;
+            let part = current(filter).unwrap_or(0.0);
+            (total != 0.0).then(|| part * 100.0 / total)
+        }
+        ValueKey::MovingAvg(filter, window) => stat(filter, &|history, current| {
+            history.average(now.saturating_sub(window.saturating_mul(1000)), current)
+        }),
+        ValueKey::Diff(filter) => stat(filter, &|history, current| history.diff(current)),
+        ValueKey::Series(_) | ValueKey::Quantile(..) => None,
+    }
+}
+
+/// Sums the samples of the series selected by the filter per sampling interval, or their
+/// change per second with `rate`.
+fn sampled_series<T>(map: &SeriesMap<T>, filter: &SeriesFilter, rate: bool) -> BTreeMap<u64, f64> {
+    let mut res = BTreeMap::new();
+    for series in map
+        .get(&filter.name)
+        .into_iter()
+        .flat_map(|series| series.values())
+        .filter(|s| filter.matches(&s.labels))
+    {
+        let samples: BTreeMap<u64, f64> = match rate {
+            true => series.history.rates().map(interval_sample).collect(),
+            false => series.history.samples().map(interval_sample).collect(),
+        };
+        for (ts, value) in samples {
+            *res.entry(ts).or_default() += value;
+        }
+    }
+    res
+}
+
+/// Moves a sample to the start of its sampling interval, like the histogram samples.
+fn interval_sample((ts, value): (u64, f64)) -> (u64, f64) {
+    (ts - ts % SAMPLE_INTERVAL_MS, value)
+}
+
+/// Values of a key per sampling interval, for the keys of a series, a rate or a quantile.
+fn sampled_values(
+    storage: &DashboardStorage,
+    metrics: &HashMap<String, MetricMeta>,
+    key: &ValueKey,
+) -> Option<BTreeMap<u64, f64>> {
+    let (filter, rate) = match key {
+        ValueKey::Series(filter) => (filter, false),
+        ValueKey::Rate(filter) => (filter, true),
+        ValueKey::Quantile(q, filter) => {
+            return match metrics.get(&filter.name)?.typ {
+                MetricType::Histogram => Some(
+                    merge_histograms(&storage.histograms, filter)
+                        .into_iter()
+                        .filter_map(|(ts, counts)| Some((ts, bucket_quantile(&counts, *q)?)))
+                        .collect(),
+                ),
+                _ => None,
+            };
+        }
+        _ => return None,
+    };
+    Some(match metrics.get(&filter.name)?.typ {
+        MetricType::Counter => sampled_series(&storage.counters, filter, rate),
+        MetricType::Gauge => sampled_series(&storage.gauges, filter, rate),
+        MetricType::Histogram => sampled_series(&storage.histograms, filter, rate),
+    })
+}
+
+/// Scales and rounds a value and the values of its series like the display of its metric.
+fn display_value(display: &MetricDisplay, value: &mut MetricValue) {
+    if display.scale.is_none() && display.precision.is_none() {
+        return;
     }
+    if let Some(v) = value.value_f64.or(value.value_u64.map(|v| v as f64)) {
+        value.value_u64 = None;
+        value.value_f64 = Some(display.value(v));
+    }
+    for series in value.series.iter_mut().flatten() {
+        display_value(display, series);
+    }
+}
 
-    fn get_gauge(&mut self, key: &str) -> SimpleGauge {
-        let entry = self.gauges.entry(key.to_string()).or_default();
-        entry.clone()
+/// Compares two rows by a column, which is `value`, `rate`, `min`, `max` or a label name.
+fn compare_rows(a: &SeriesRow, b: &SeriesRow, column: &str) -> Ordering {
+    let number = |row: &SeriesRow| match column {
+        "value" => Some(row.value),
+        "rate" => row.rate,
+        "min" => row.min,
+        "max" => row.max,
+        _ => None,
+    };
+    match column {
+        "value" | "rate" | "min" | "max" => {
+            number(a).partial_cmp(&number(b)).unwrap_or(Ordering::Equal)
+        }
+        label => a.labels.get(label).cmp(&b.labels.get(label)),
     }
+}
+
+fn get_series<T: Default + Clone>(map: &mut SeriesMap<T>, key: &Key) -> T {
+    let labels = key_labels(key);
+    let entry = map
+        .entry(key.name().to_string())
+        .or_default()
+        .entry(series_key(key.name(), &labels))
+        .or_insert_with(|| Series {
+            labels,
+            handle: T::default(),
+            history: SeriesHistory::default(),
+        });
+    entry.handle.clone()
+}
+
+/// Sums the histogram samples of the series selected by the filter, by sample time.
+fn merge_histograms(
+    map: &SeriesMap<SimpleHistogram>,
+    filter: &SeriesFilter,
+) -> BTreeMap<u64, Vec<u64>> {
+    let mut merged: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    for histogram in filter_series(map, filter) {
+        for sample in histogram.samples() {
+            let counts = merged
+                .entry(sample.ts)
+                .or_insert_with(|| vec![0; sample.counts.len()]);
+            for (count, value) in counts.iter_mut().zip(sample.counts) {
+                *count += value;
+            }
+        }
+    }
+    merged
+}
+
+/// Returns the handles of the series selected by the filter.
+fn filter_series<'a, T>(
+    map: &'a SeriesMap<T>,
+    filter: &'a SeriesFilter,
+) -> impl Iterator<Item = &'a T> + 'a {
+    keyed_series(map, filter).map(|(_, handle)| handle)
+}
+
+/// Returns the series keys and handles of the series selected by the filter.
+fn keyed_series<'a, T>(
+    map: &'a SeriesMap<T>,
+    filter: &'a SeriesFilter,
+) -> impl Iterator<Item = (&'a String, &'a T)> + 'a {
+    map.get(&filter.name)
+        .into_iter()
+        .flat_map(|series| series.iter())
+        .filter(|(_, s)| filter.matches(&s.labels))
+        .map(|(key, s)| (key, &s.handle))
+}
+
+/// Returns the labels of every series of a metric.
+fn series_labels<'a, T>(map: &'a SeriesMap<T>, name: &str) -> Vec<&'a Labels> {
+    map.get(name)
+        .into_iter()
+        .flat_map(|series| series.values())
+        .map(|s| &s.labels)
+        .collect()
+}
 
-    fn get_histogram(&mut self, key: &str) -> SimpleHistogram {
-        let entry = self.histograms.entry(key.to_string()).or_default();
-        entry.clone()
+/// Builds the value of a key from the handles of the series it selects.
+/// For a selector, the value of each series is added too.
+fn selected_value<T>(
+    map: &SeriesMap<T>,
+    filter: &SeriesFilter,
+    key: &str,
+    value: impl Fn(String, &mut dyn Iterator<Item = &T>) -> MetricValue,
+) -> MetricValue {
+    let mut res = value(key.to_string(), &mut filter_series(map, filter));
+    if filter.is_selector() {
+        let mut series: Vec<MetricValue> = keyed_series(map, filter)
+            .map(|(key, handle)| value(key.clone(), &mut std::iter::once(handle)))
+            .collect();
+        series.sort_by(|a, b| a.key.cmp(&b.key));
+        res.series = Some(series);
     }
+    res
 }
 
 #[derive(Clone)]
 pub struct DashboardRecorder {
     pub options: DashboardOptions,
+    /// Charts of the dashboard, which can be edited at runtime.
+    pub(crate) charts: Arc<RwLock<ChartSet>>,
     storage: Arc<RwLock<DashboardStorage>>,
     metrics: Arc<RwLock<HashMap<String, MetricMeta>>>,
+    annotations: Arc<RwLock<AnnotationLog>>,
 }
 
 /// The `DashboardRecorder` struct represents a recorder for metrics dashboard.
 /// It provides methods for adding bound keys, retrieving metrics, and retrieving metric values.
 impl DashboardRecorder {
     /// Creates a new instance of `DashboardRecorder`.
+    /// A background thread samples the value of every series until the recorder is dropped,
+    /// to keep their history over the last minutes.
     ///
     /// # Returns
     ///
     /// A new instance of `DashboardRecorder`.
     pub fn new(opts: DashboardOptions) -> Self {
+        let storage: Arc<RwLock<DashboardStorage>> = Default::default();
+        let weak_storage = Arc::downgrade(&storage);
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(SAMPLE_INTERVAL_MS));
+            let Some(storage) = weak_storage.upgrade() else {
+                break;
+            };
+            storage.write().sample(now_ms());
+        });
+
         Self {
+            charts: Arc::new(RwLock::new(ChartSet::new(&opts))),
             options: opts,
-            storage: Default::default(),
+            storage,
             metrics: Arc::new(RwLock::new(HashMap::new())),
+            annotations: Default::default(),
         }
     }
 
+    /// Returns the error of loading the persisted charts file of the options, if any.
+    /// The configured charts are shown instead and the edits are refused, so the file
+    /// is not overwritten.
+    pub fn persist_error(&self) -> Option<ConfigError> {
+        self.charts.read().persist_error()
+    }
+
     /// Retrieves the metrics as a vector of `MetricMeta`.
     ///
     /// # Returns
@@ -90,14 +477,44 @@ impl DashboardRecorder {
     pub fn metrics(&self) -> Vec<MetricMeta> {
         let mut res = vec![];
         let metrics = &*self.metrics.read();
-        for (_key, meta) in metrics.iter() {
-            res.push(meta.clone());
+        for (key, meta) in metrics.iter() {
+            let mut meta = meta.clone();
+            if let Some(display) = self.options.metric_display.get(key) {
+                display.apply(&mut meta);
+            }
+            res.push(meta);
         }
         res.sort_by_cached_key(|m: &MetricMeta| m.key.clone());
         res
     }
 
+    /// Retrieves the metrics shown in the dashboard, without the ones hidden by the
+    /// `include_metrics` and `exclude_metrics` options.
+    pub fn visible_metrics(&self) -> Vec<MetricMeta> {
+        let visibility =
+            MetricVisibility::new(&self.options.include_metrics, &self.options.exclude_metrics);
+        let storage = self.storage.read();
+        let mut metas = self.metrics();
+        metas.retain(|meta| {
+            let series: Vec<&Labels> = match meta.typ {
+                MetricType::Counter => series_labels(&storage.counters, &meta.key),
+                MetricType::Gauge => series_labels(&storage.gauges, &meta.key),
+                MetricType::Histogram => series_labels(&storage.histograms, &meta.key),
+            };
+            visibility.is_visible(meta, &series)
+        });
+        metas
+    }
+
     /// Retrieves the metric values for the specified keys.
+    /// A key is either a metric name or a series like `name{label="value"}`,
+    /// the values of all series matching the key are summed up.
+    /// For a selector like `name{status=~"5.."}`, the value of each matching series is
+    /// returned in `series` too.
+    /// The key `quantile(0.99, name)` retrieves a quantile of a histogram over the last minutes.
+    /// Values derived from the history of the series are computed by the functions
+    /// `rate(name)`, `ratio(a, b)`, `percent(name{label="value"})`, `moving_avg(name, 60)`
+    /// and `diff(name)`.
     ///
     /// # Arguments
     ///
@@ -107,41 +524,267 @@ impl DashboardRecorder {
     ///
     /// A vector of `MetricValue`.
     pub fn metrics_value(&self, keys: Vec<&str>) -> Vec<MetricValue> {
-        let mut storage = self.storage.write();
+        let storage = self.storage.read();
         let metrics = self.metrics.read();
         let mut data = vec![];
         for key in keys {
-            if let Some(meta) = metrics.get(key) {
-                match meta.typ {
-                    MetricType::Counter => {
-                        let counter = storage.get_counter(key);
+            let filter = match ValueKey::parse(key) {
+                Some(ValueKey::Series(filter)) => filter,
+                Some(ValueKey::Quantile(q, filter)) => {
+                    if let Some(MetricType::Histogram) = metrics.get(&filter.name).map(|m| &m.typ) {
+                        let mut counts = vec![0; BUCKET_BOUNDS.len() + 1];
+                        for sample in merge_histograms(&storage.histograms, &filter).values() {
+                            for (count, value) in counts.iter_mut().zip(sample) {
+                                *count += value;
+                            }
+                        }
                         data.push(MetricValue {
                             key: key.to_string(),
-                            value_u64: Some(counter.value()),
-                            value_f64: None,
+                            value_u64: None,
+                            value_f64: bucket_quantile(&counts, q),
+                            series: None,
                         });
                     }
+                    continue;
+                }
+                Some(value_key) => {
+                    if value_key
+                        .filters()
+                        .iter()
+                        .any(|f| !metrics.contains_key(&f.name))
+                    {
+                        continue;
+                    }
+                    data.push(MetricValue {
+                        key: key.to_string(),
+                        value_u64: None,
+                        value_f64: derived_value(&storage, &metrics, &value_key),
+                        series: None,
+                    });
+                    continue;
+                }
+                None => continue,
+            };
+            if let Some(meta) = metrics.get(&filter.name) {
+                let value = match meta.typ {
+                    MetricType::Counter => {
+                        selected_value(&storage.counters, &filter, key, |key, series| MetricValue {
+                            key,
+                            value_u64: Some(series.map(|c| c.value()).sum()),
+                            value_f64: None,
+                            series: None,
+                        })
+                    }
                     MetricType::Gauge => {
-                        let gauge = storage.get_gauge(key);
-                        data.push(MetricValue {
-                            key: key.to_string(),
-                            value_u64: None,
-                            value_f64: Some((gauge.value() * 100.0).round() / 100.0),
-                        });
+                        selected_value(&storage.gauges, &filter, key, |key, series| {
+                            let value: f64 = series.map(|g| g.value()).sum();
+                            MetricValue {
+                                key,
+                                value_u64: None,
+                                value_f64: Some(value),
+                                series: None,
+                            }
+                        })
                     }
                     MetricType::Histogram => {
-                        // let _histogram = self
-                        //     .registry
-                        //     .get_or_create_histogram(&metrics::Key::from(key.to_string()), |a| {
-                        //         a.clone()
-                        //     });
-                        // TODO
+                        selected_value(&storage.histograms, &filter, key, |key, series| {
+                            MetricValue {
+                                key,
+                                value_u64: Some(series.map(|h| h.count()).sum()),
+                                value_f64: None,
+                                series: None,
+                            }
+                        })
                     }
                 };
+                data.push(value);
+            }
+        }
+        for value in data.iter_mut() {
+            if let Some(display) = self.value_display(&value.key) {
+                display_value(display, value);
             }
         }
         data
     }
+
+    /// Retrieves the bucket counts per sampling interval of a histogram.
+    /// Like in `metrics_value`, the samples of all series matching the key are summed up.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the key is not a histogram.
+    pub fn histogram_value(&self, key: &str) -> Option<HistogramValue> {
+        let filter = SeriesFilter::parse(key)?;
+        match self.metrics.read().get(&filter.name)?.typ {
+            MetricType::Histogram => {}
+            _ => return None,
+        }
+
+        let merged = merge_histograms(&self.storage.read().histograms, &filter);
+
+        let buckets = 0..BUCKET_BOUNDS.len() + 1;
+        let used = |i: &usize| merged.values().any(|counts| counts[*i] > 0);
+        let range = match (buckets.clone().find(used), buckets.rev().find(used)) {
+            (Some(first), Some(last)) => first..last + 1,
+            _ => 0..0,
+        };
+
+        Some(HistogramValue {
+            key: key.to_string(),
+            bounds: range
+                .clone()
+                .map(|i| BUCKET_BOUNDS.get(i).copied())
+                .collect(),
+            interval_ms: SAMPLE_INTERVAL_MS,
+            samples: merged
+                .into_iter()
+                .map(|(ts, counts)| HistogramSample {
+                    ts,
+                    counts: counts[range.clone()].to_vec(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Retrieves the series of a metric as table rows, with their current value, rate,
+    /// minimum and maximum over the last minutes. The key selects series like in `metrics_value`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The series to list.
+    /// * `sort` - The column to sort by: `value`, `rate`, `min`, `max` or a label name.
+    /// * `descending` - Whether the greatest values come first.
+    /// * `limit` - The maximum number of rows.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the metric is unknown.
+    pub fn table_value(
+        &self,
+        key: &str,
+        sort: &str,
+        descending: bool,
+        limit: usize,
+    ) -> Option<TableValue> {
+        let filter = SeriesFilter::parse(key)?;
+        let typ = self.metrics.read().get(&filter.name)?.typ.clone();
+        let storage = self.storage.read();
+        let mut rows = match typ {
+            MetricType::Counter => series_rows(&storage.counters, &filter, |c| c.value() as f64),
+            MetricType::Gauge => series_rows(&storage.gauges, &filter, |g| g.value()),
+            MetricType::Histogram => {
+                series_rows(&storage.histograms, &filter, |h| h.count() as f64)
+            }
+        };
+        drop(storage);
+
+        let mut labels: Vec<String> = rows
+            .iter()
+            .flat_map(|row| row.labels.keys().cloned())
+            .collect();
+        labels.sort();
+        labels.dedup();
+
+        rows.sort_by(|a, b| match descending {
+            true => compare_rows(b, a, sort),
+            false => compare_rows(a, b, sort),
+        });
+        let total = rows.len();
+        rows.truncate(limit);
+        if let Some(display) = self.value_display(key) {
+            for row in rows.iter_mut() {
+                row.value = display.value(row.value);
+                for value in [&mut row.rate, &mut row.min, &mut row.max] {
+                    *value = value.map(|v| display.value(v));
+                }
+            }
+        }
+
+        Some(TableValue {
+            key: key.to_string(),
+            labels,
+            total,
+            rows,
+        })
+    }
+
+    /// Retrieves the values of two keys sampled in the same intervals over the last minutes,
+    /// to plot one against the other. A key is a series like in `metrics_value`, summing the
+    /// samples of the series it selects, or a `rate(name)` or `quantile(0.99, name)` function.
+    /// With `trend`, the line fitting the points and their correlation are computed too.
+    ///
+    /// # Returns
+    ///
+    /// `None` if a key is not supported or its metric is unknown.
+    pub fn scatter_value(&self, x: &str, y: &str, trend: bool) -> Option<ScatterValue> {
+        let (x_key, y_key) = (ValueKey::parse(x)?, ValueKey::parse(y)?);
+        let storage = self.storage.read();
+        let metrics = self.metrics.read();
+        let x_values = sampled_values(&storage, &metrics, &x_key)?;
+        let y_values = sampled_values(&storage, &metrics, &y_key)?;
+        drop(storage);
+
+        let scale = |key: &str, value: f64| match self.value_display(key) {
+            Some(display) => display.value(value),
+            None => value,
+        };
+        let points = x_values
+            .iter()
+            .filter_map(|(ts, x_value)| Some([scale(x, *x_value), scale(y, *y_values.get(ts)?)]))
+            .collect();
+        Some(ScatterValue::new(x, y, points, trend))
+    }
+
+    /// The display of the metric of a key, except for ratios and shares which have no unit.
+    fn value_display(&self, key: &str) -> Option<&MetricDisplay> {
+        let filter = match ValueKey::parse(key)? {
+            ValueKey::Ratio(..) | ValueKey::Percent(_) => return None,
+            ValueKey::Series(filter)
+            | ValueKey::Quantile(_, filter)
+            | ValueKey::Rate(filter)
+            | ValueKey::MovingAvg(filter, _)
+            | ValueKey::Diff(filter) => filter,
+        };
+        self.options.metric_display.get(&filter.name)
+    }
+
+    /// Records an event like a deployment at the current time, marked on the line charts:
+    ///
+    /// ```rust,ignore
+    /// let (dashboard, route) = build_dashboard_route_with_recorder(options);
+    /// dashboard.annotate("deploy v1.2", ["deploy"]);
+    /// ```
+    pub fn annotate(
+        &self,
+        text: impl Into<String>,
+        tags: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Annotation {
+        self.add_annotation(Annotation {
+            id: 0,
+            ts: now_ms(),
+            text: text.into(),
+            tags: tags.into_iter().map(Into::into).collect(),
+        })
+    }
+
+    /// Records an event at the time of the annotation, and returns it with its sequence number.
+    /// Only the last 1000 events are kept.
+    pub fn add_annotation(&self, annotation: Annotation) -> Annotation {
+        self.annotations.write().push(annotation)
+    }
+
+    /// Retrieves the annotations at or after `since`, in milliseconds since the unix epoch,
+    /// oldest first.
+    pub fn annotations(&self, since: Option<u64>) -> Vec<Annotation> {
+        self.annotations.read().since(since)
+    }
+
+    /// Retrieves the annotations recorded after the one numbered `id`, oldest first,
+    /// including the ones recorded late with an earlier timestamp.
+    pub fn annotations_after(&self, id: u64) -> Vec<Annotation> {
+        self.annotations.read().after(id)
+    }
 }
 
 impl Recorder for DashboardRecorder {
@@ -162,6 +805,9 @@ impl Recorder for DashboardRecorder {
                     typ: MetricType::Counter,
                     desc: Some(description.to_string()),
                     unit: unit.map(|u| u.as_canonical_label().to_string()),
+                    target: None,
+                    display_name: None,
+                    precision: None,
                 },
             );
         }
@@ -184,6 +830,9 @@ impl Recorder for DashboardRecorder {
                     typ: MetricType::Gauge,
                     desc: Some(description.to_string()),
                     unit: unit.map(|u| u.as_canonical_label().to_string()),
+                    target: None,
+                    display_name: None,
+                    precision: None,
                 },
             );
         }
@@ -206,62 +855,216 @@ impl Recorder for DashboardRecorder {
                     typ: MetricType::Histogram,
                     desc: Some(description.to_string()),
                     unit: unit.map(|u| u.as_canonical_label().to_string()),
+                    target: None,
+                    display_name: None,
+                    precision: None,
                 },
             );
         }
     }
 
-    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> metrics::Counter {
+    fn register_counter(&self, key: &Key, metadata: &Metadata<'_>) -> metrics::Counter {
         let mut metrics = self.metrics.write();
-        if !metrics.contains_key(key.name()) {
-            metrics.insert(
-                key.name().to_string(),
-                MetricMeta {
-                    key: key.name().to_string(),
-                    typ: MetricType::Counter,
-                    desc: None,
-                    unit: None,
-                },
-            );
-        }
+        metrics
+            .entry(key.name().to_string())
+            .or_insert_with(|| MetricMeta {
+                key: key.name().to_string(),
+                typ: MetricType::Counter,
+                desc: None,
+                unit: None,
+                target: None,
+                display_name: None,
+                precision: None,
+            })
+            .target
+            .get_or_insert_with(|| metadata.target().to_string());
         drop(metrics);
 
-        metrics::Counter::from_arc(self.storage.write().get_counter(key.name()).into())
+        metrics::Counter::from_arc(self.storage.write().get_counter(key).into())
     }
 
-    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> metrics::Gauge {
+    fn register_gauge(&self, key: &Key, metadata: &Metadata<'_>) -> metrics::Gauge {
         let mut metrics = self.metrics.write();
-        if !metrics.contains_key(key.name()) {
-            metrics.insert(
-                key.name().to_string(),
-                MetricMeta {
-                    key: key.name().to_string(),
-                    typ: MetricType::Gauge,
-                    desc: None,
-                    unit: None,
-                },
-            );
-        }
+        metrics
+            .entry(key.name().to_string())
+            .or_insert_with(|| MetricMeta {
+                key: key.name().to_string(),
+                typ: MetricType::Gauge,
+                desc: None,
+                unit: None,
+                target: None,
+                display_name: None,
+                precision: None,
+            })
+            .target
+            .get_or_insert_with(|| metadata.target().to_string());
         drop(metrics);
 
-        metrics::Gauge::from_arc(self.storage.write().get_gauge(key.name()).into())
+        metrics::Gauge::from_arc(self.storage.write().get_gauge(key).into())
     }
 
-    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> metrics::Histogram {
+    fn register_histogram(&self, key: &Key, metadata: &Metadata<'_>) -> metrics::Histogram {
         let mut metrics = self.metrics.write();
-        if !metrics.contains_key(key.name()) {
-            metrics.insert(
-                key.name().to_string(),
+        metrics
+            .entry(key.name().to_string())
+            .or_insert_with(|| MetricMeta {
+                key: key.name().to_string(),
+                typ: MetricType::Histogram,
+                desc: None,
+                unit: None,
+                target: None,
+                display_name: None,
+                precision: None,
+            })
+            .target
+            .get_or_insert_with(|| metadata.target().to_string());
+        drop(metrics);
+
+        metrics::Histogram::from_arc(self.storage.write().get_histogram(key).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use metrics::{CounterFn, GaugeFn, Label};
+
+    use super::{histogram::MAX_SAMPLES, *};
+
+    /// Storage with hand-built series, whose history is given as values sampled
+    /// every interval until the last one, one interval ago.
+    #[derive(Default)]
+    struct TestStorage {
+        storage: DashboardStorage,
+        metrics: HashMap<String, MetricMeta>,
+    }
+
+    impl TestStorage {
+        fn meta(&mut self, name: &str, typ: MetricType) {
+            self.metrics.insert(
+                name.to_string(),
                 MetricMeta {
-                    key: key.name().to_string(),
-                    typ: MetricType::Histogram,
+                    key: name.to_string(),
+                    typ,
                     desc: None,
                     unit: None,
+                    target: None,
+                    display_name: None,
+                    precision: None,
                 },
             );
         }
-        drop(metrics);
 
-        metrics::Histogram::from_arc(self.storage.write().get_histogram(key.name()).into())
+        fn counter(&mut self, name: &str, labels: &[(&str, &str)], history: &[f64], current: u64) {
+            self.meta(name, MetricType::Counter);
+            let key = test_key(name, labels);
+            self.storage.get_counter(&key).absolute(current);
+            push_history(&mut self.storage.counters, &key, history);
+        }
+
+        fn gauge(&mut self, name: &str, labels: &[(&str, &str)], history: &[f64], current: f64) {
+            self.meta(name, MetricType::Gauge);
+            let key = test_key(name, labels);
+            self.storage.get_gauge(&key).set(current);
+            push_history(&mut self.storage.gauges, &key, history);
+        }
+
+        fn value(&self, key: &str) -> Option<f64> {
+            let key = ValueKey::parse(key).expect("valid key");
+            derived_value(&self.storage, &self.metrics, &key)
+        }
+    }
+
+    fn test_key(name: &str, labels: &[(&str, &str)]) -> Key {
+        let labels: Vec<Label> = labels
+            .iter()
+            .map(|(l, v)| Label::new(l.to_string(), v.to_string()))
+            .collect();
+        Key::from_parts(name.to_string(), labels)
+    }
+
+    fn push_history<T>(map: &mut SeriesMap<T>, key: &Key, history: &[f64]) {
+        let series_key = series_key(key.name(), &key_labels(key));
+        let series = map
+            .get_mut(key.name())
+            .and_then(|series| series.get_mut(&series_key))
+            .expect("registered series");
+        let now = now_ms();
+        for (i, value) in history.iter().enumerate() {
+            let age = (history.len() - i) as u64 * SAMPLE_INTERVAL_MS;
+            series.history.push(now - age, *value);
+        }
+    }
+
+    fn assert_close(value: Option<f64>, expected: f64) {
+        let value = value.expect("a value");
+        assert!(
+            (value - expected).abs() < 0.01,
+            "expected {expected}, got {value}"
+        );
+    }
+
+    #[test]
+    fn ratio_of_zero_is_none() {
+        let mut test = TestStorage::default();
+        // No request in the last interval
+        test.counter("errors", &[], &[5.0], 5);
+        test.counter("requests", &[], &[100.0], 100);
+        test.gauge("used", &[], &[], 3.0);
+        test.gauge("capacity", &[], &[], 0.0);
+        assert_eq!(test.value("ratio(errors, requests)"), None);
+        assert_eq!(test.value("ratio(used, capacity)"), None);
+        assert_close(test.value("ratio(capacity, used)"), 0.0);
+    }
+
+    #[test]
+    fn ratio_of_counters_follows_the_last_interval() {
+        let mut test = TestStorage::default();
+        // 1% of errors since the start, but half of the requests of the last interval
+        test.counter("errors", &[], &[0.0], 10);
+        test.counter("requests", &[], &[980.0], 1000);
+        assert_close(test.value("ratio(errors, requests)"), 0.5);
+    }
+
+    #[test]
+    fn moving_average_window_longer_than_history() {
+        let max_window = MAX_SAMPLES as u64 * SAMPLE_INTERVAL_MS / 1000;
+        assert!(matches!(
+            ValueKey::parse("moving_avg(queue, 18446744073709552)"),
+            Some(ValueKey::MovingAvg(_, window)) if window == max_window
+        ));
+
+        let mut test = TestStorage::default();
+        test.gauge("queue", &[], &[1.0, 2.0, 3.0], 6.0);
+        // The whole history and the current value
+        assert_close(test.value("moving_avg(queue, 18446744073709552)"), 3.0);
+        assert_close(test.value("moving_avg(queue, 3600)"), 3.0);
+        // Only the last sample and the current value
+        assert_close(test.value("moving_avg(queue, 7)"), 4.5);
+    }
+
+    #[test]
+    fn percent_of_counters_and_gauges() {
+        let mut test = TestStorage::default();
+        // Counters are compared by their rate: 10 of the 40 requests of the last interval
+        test.counter("requests", &[("status", "500")], &[0.0], 10);
+        test.counter("requests", &[("status", "200")], &[1000.0], 1030);
+        assert_close(test.value(r#"percent(requests{status="500"})"#), 25.0);
+        // Gauges by their current value
+        test.gauge("workers", &[("state", "busy")], &[1.0], 3.0);
+        test.gauge("workers", &[("state", "idle")], &[9.0], 9.0);
+        assert_close(test.value(r#"percent(workers{state="busy"})"#), 25.0);
+        // A selector matching no series is a share of zero
+        assert_close(test.value(r#"percent(workers{state="gone"})"#), 0.0);
+    }
+
+    #[test]
+    fn diff_needs_a_sample() {
+        let mut test = TestStorage::default();
+        test.gauge("fresh", &[], &[], 4.0);
+        assert_eq!(test.value("diff(fresh)"), None);
+        test.gauge("sampled", &[], &[4.0], 6.5);
+        assert_close(test.value("diff(sampled)"), 2.5);
+        test.counter("total", &[], &[10.0], 12);
+        assert_close(test.value("diff(total)"), 2.0);
     }
 }
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of annotations kept, the oldest ones are dropped first.
+const MAX_ANNOTATIONS: usize = 1000;
+
+/// A timestamped event like a deployment or an incident, marked on the charts.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Annotation {
+    /// Sequence number assigned when the annotation is recorded, increasing in the order of
+    /// recording whatever the timestamp.
+    #[serde(default)]
+    pub id: u64,
+    /// Milliseconds since the unix epoch.
+    pub ts: u64,
+    pub text: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Annotations sorted by time.
+#[derive(Debug, Default)]
+pub struct AnnotationLog {
+    annotations: VecDeque<Annotation>,
+    last_id: u64,
+}
+
+impl AnnotationLog {
+    /// Records the annotation with the next sequence number, and returns it.
+    pub fn push(&mut self, mut annotation: Annotation) -> Annotation {
+        self.last_id += 1;
+        annotation.id = self.last_id;
+        let position = self.annotations.partition_point(|a| a.ts <= annotation.ts);
+        self.annotations.insert(position, annotation.clone());
+        if self.annotations.len() > MAX_ANNOTATIONS {
+            self.annotations.pop_front();
+        }
+        annotation
+    }
+
+    /// Returns the annotations at or after `since`, or all of them.
+    pub fn since(&self, since: Option<u64>) -> Vec<Annotation> {
+        let start = since.map_or(0, |since| {
+            self.annotations.partition_point(|a| a.ts < since)
+        });
+        self.annotations.range(start..).cloned().collect()
+    }
+
+    /// Returns the annotations recorded after the one numbered `id`, by time.
+    pub fn after(&self, id: u64) -> Vec<Annotation> {
+        self.annotations
+            .iter()
+            .filter(|a| a.id > id)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotation(ts: u64, text: &str) -> Annotation {
+        Annotation {
+            id: 0,
+            ts,
+            text: text.to_string(),
+            tags: vec![],
+        }
+    }
+
+    #[test]
+    fn numbers_in_the_order_of_recording() {
+        let mut log = AnnotationLog::default();
+        assert_eq!(log.push(annotation(20, "deploy")).id, 1);
+        assert_eq!(log.push(annotation(10, "late")).id, 2);
+        let texts: Vec<_> = log.since(None).into_iter().map(|a| a.text).collect();
+        assert_eq!(texts, ["late", "deploy"]);
+    }
+
+    #[test]
+    fn late_annotations_follow_the_last_seen() {
+        let mut log = AnnotationLog::default();
+        let seen = log.push(annotation(20, "deploy"));
+        log.push(annotation(10, "late"));
+        log.push(annotation(30, "rollback"));
+        assert!(log
+            .since(Some(seen.ts + 1))
+            .iter()
+            .all(|a| a.text != "late"));
+        let texts: Vec<_> = log.after(seen.id).into_iter().map(|a| a.text).collect();
+        assert_eq!(texts, ["late", "rollback"]);
+    }
+}
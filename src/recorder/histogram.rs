@@ -1,10 +1,126 @@
+use std::{collections::VecDeque, sync::Arc};
+
 use metrics::HistogramFn;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use super::history::now_ms;
+
+/// Length of a sampling interval, in milliseconds.
+pub const SAMPLE_INTERVAL_MS: u64 = 5_000;
+/// Number of sampling intervals kept for each histogram.
+pub const MAX_SAMPLES: usize = 60;
+
+/// Upper bounds of the buckets, a last bucket without bound holds the greater values.
+/// The 1-2.5-5 steps cover from microseconds to gigabytes.
+pub const BUCKET_BOUNDS: [f64; 48] = [
+    1e-6, 2.5e-6, 5e-6, //
+    1e-5, 2.5e-5, 5e-5, //
+    1e-4, 2.5e-4, 5e-4, //
+    1e-3, 2.5e-3, 5e-3, //
+    1e-2, 2.5e-2, 5e-2, //
+    1e-1, 2.5e-1, 5e-1, //
+    1e0, 2.5e0, 5e0, //
+    1e1, 2.5e1, 5e1, //
+    1e2, 2.5e2, 5e2, //
+    1e3, 2.5e3, 5e3, //
+    1e4, 2.5e4, 5e4, //
+    1e5, 2.5e5, 5e5, //
+    1e6, 2.5e6, 5e6, //
+    1e7, 2.5e7, 5e7, //
+    1e8, 2.5e8, 5e8, //
+    1e9, 2.5e9, 5e9, //
+];
+
+/// Bucket counts of the values recorded during one sampling interval.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistogramSample {
+    /// Start of the interval, in milliseconds since the unix epoch.
+    pub ts: u64,
+    pub counts: Vec<u64>,
+}
+
+impl HistogramSample {
+    fn new(ts: u64) -> Self {
+        Self {
+            ts,
+            counts: vec![0; BUCKET_BOUNDS.len() + 1],
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct HistogramData {
+    count: u64,
+    samples: VecDeque<HistogramSample>,
+}
 
 #[derive(Debug, Clone, Default)]
-pub struct SimpleHistogram {}
+pub struct SimpleHistogram {
+    data: Arc<Mutex<HistogramData>>,
+}
+
+impl SimpleHistogram {
+    /// Number of recorded values since the start.
+    pub fn count(&self) -> u64 {
+        self.data.lock().count
+    }
+
+    /// Samples of the last `MAX_SAMPLES` intervals, oldest first. Intervals without values are omitted.
+    pub fn samples(&self) -> Vec<HistogramSample> {
+        let oldest = current_interval().saturating_sub(SAMPLE_INTERVAL_MS * MAX_SAMPLES as u64);
+        let data = self.data.lock();
+        data.samples
+            .iter()
+            .filter(|s| s.ts > oldest)
+            .cloned()
+            .collect()
+    }
+}
 
 impl HistogramFn for SimpleHistogram {
-    fn record(&self, _value: f64) {
-        //TODO
+    fn record(&self, value: f64) {
+        let ts = current_interval();
+        let bucket = BUCKET_BOUNDS.partition_point(|bound| *bound < value);
+        let mut data = self.data.lock();
+        data.count += 1;
+        if data.samples.back().map(|s| s.ts) != Some(ts) {
+            data.samples.push_back(HistogramSample::new(ts));
+            if data.samples.len() > MAX_SAMPLES {
+                data.samples.pop_front();
+            }
+        }
+        let sample = data.samples.back_mut().expect("Should have current sample");
+        sample.counts[bucket] += 1;
     }
 }
+
+/// Estimates the quantile `q` of the values counted in the buckets, by interpolating linearly
+/// inside the bucket containing it. Returns the last bound for values over all buckets.
+pub fn bucket_quantile(counts: &[u64], q: f64) -> Option<f64> {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let rank = q * total as f64;
+    let mut seen = 0;
+    for (i, count) in counts.iter().enumerate() {
+        if *count == 0 || ((seen + count) as f64) < rank {
+            seen += count;
+            continue;
+        }
+        let Some(upper) = BUCKET_BOUNDS.get(i) else {
+            return BUCKET_BOUNDS.last().copied();
+        };
+        let lower = if i == 0 { 0.0 } else { BUCKET_BOUNDS[i - 1] };
+        let ratio = (rank - seen as f64) / *count as f64;
+        return Some(lower + (upper - lower) * ratio);
+    }
+    BUCKET_BOUNDS.last().copied()
+}
+
+/// Start of the current sampling interval, in milliseconds since the unix epoch.
+fn current_interval() -> u64 {
+    let now = now_ms();
+    now - now % SAMPLE_INTERVAL_MS
+}
@@ -0,0 +1,95 @@
+use std::{
+    collections::VecDeque,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::histogram::MAX_SAMPLES;
+
+/// Values of a series sampled at each sampling interval, over the last minutes.
+#[derive(Debug, Clone, Default)]
+pub struct SeriesHistory {
+    samples: VecDeque<(u64, f64)>,
+}
+
+impl SeriesHistory {
+    pub fn push(&mut self, ts: u64, value: f64) {
+        self.samples.push_back((ts, value));
+        if self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Samples as timestamp and value, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = (u64, f64)> + '_ {
+        self.samples.iter().copied()
+    }
+
+    /// Change per second between each sample and the previous one, at the time of the sample.
+    pub fn rates(&self) -> impl Iterator<Item = (u64, f64)> + '_ {
+        self.samples
+            .iter()
+            .zip(self.samples.iter().skip(1))
+            .filter(|((ts, _), (next_ts, _))| next_ts > ts)
+            .map(|((ts, value), (next_ts, next))| {
+                (*next_ts, (next - value) * 1000.0 / (next_ts - ts) as f64)
+            })
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.samples.iter().map(|(_, v)| *v).reduce(f64::min)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.samples.iter().map(|(_, v)| *v).reduce(f64::max)
+    }
+
+    /// Change per second between the oldest and the newest samples.
+    pub fn rate(&self) -> Option<f64> {
+        let (first_ts, first) = self.samples.front()?;
+        let (last_ts, last) = self.samples.back()?;
+        if last_ts <= first_ts {
+            return None;
+        }
+        Some((last - first) * 1000.0 / (last_ts - first_ts) as f64)
+    }
+
+    /// Change per second between the newest sample at least `interval` milliseconds old,
+    /// or the oldest one, and the current value.
+    pub fn recent_rate(&self, now: u64, current: f64, interval: u64) -> Option<f64> {
+        let (ts, value) = self
+            .samples
+            .iter()
+            .rev()
+            .find(|(ts, _)| now.saturating_sub(*ts) >= interval)
+            .or(self.samples.front())?;
+        if now <= *ts {
+            return None;
+        }
+        Some((current - value) * 1000.0 / (now - ts) as f64)
+    }
+
+    /// Change between the newest sample and the current value.
+    pub fn diff(&self, current: f64) -> Option<f64> {
+        self.samples.back().map(|(_, last)| current - last)
+    }
+
+    /// Average of the samples at or after `since` and the current value.
+    pub fn average(&self, since: u64, current: f64) -> Option<f64> {
+        let values: Vec<f64> = self
+            .samples
+            .iter()
+            .filter(|(ts, _)| *ts >= since)
+            .map(|(_, v)| *v)
+            .chain(std::iter::once(current))
+            .collect();
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// Milliseconds since the unix epoch.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,82 @@
+use serde::Serialize;
+
+/// Pairs of values of two metrics sampled in the same intervals, over the last minutes.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScatterValue {
+    /// Key of the values on the x-axis.
+    pub x: String,
+    /// Key of the values on the y-axis.
+    pub y: String,
+    /// Values of `x` and `y` of each sampling interval, oldest first.
+    pub points: Vec<[f64; 2]>,
+    /// Least squares line fitting the points, when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trend: Option<TrendLine>,
+    /// Pearson correlation coefficient of the points, between -1 and 1, when the trend is
+    /// requested and both values vary.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation: Option<f64>,
+}
+
+/// The line `y = slope * x + intercept`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TrendLine {
+    pub slope: f64,
+    pub intercept: f64,
+}
+
+impl ScatterValue {
+    pub fn new(x: &str, y: &str, points: Vec<[f64; 2]>, trend: bool) -> Self {
+        let (trend, correlation) = match trend {
+            true => (linear_fit(&points), correlation(&points)),
+            false => (None, None),
+        };
+        Self {
+            x: x.to_string(),
+            y: y.to_string(),
+            points,
+            trend,
+            correlation,
+        }
+    }
+}
+
+/// Means of x and y, and the sums of the squared deviations of x and y and of their products.
+fn moments(points: &[[f64; 2]]) -> Option<(f64, f64, f64, f64, f64)> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p[0]).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p[1]).sum::<f64>() / n;
+    let (mut sxx, mut syy, mut sxy) = (0.0, 0.0, 0.0);
+    for [x, y] in points {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        sxx += dx * dx;
+        syy += dy * dy;
+        sxy += dx * dy;
+    }
+    Some((mean_x, mean_y, sxx, syy, sxy))
+}
+
+/// Least squares fit of the points, `None` when x does not vary.
+fn linear_fit(points: &[[f64; 2]]) -> Option<TrendLine> {
+    let (mean_x, mean_y, sxx, _, sxy) = moments(points)?;
+    if sxx == 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    Some(TrendLine {
+        slope,
+        intercept: mean_y - slope * mean_x,
+    })
+}
+
+/// Pearson correlation coefficient, `None` when x or y does not vary.
+fn correlation(points: &[[f64; 2]]) -> Option<f64> {
+    let (_, _, sxx, syy, sxy) = moments(points)?;
+    if sxx == 0.0 || syy == 0.0 {
+        return None;
+    }
+    Some((sxy / (sxx * syy).sqrt()).clamp(-1.0, 1.0))
+}
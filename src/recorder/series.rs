@@ -0,0 +1,419 @@
+use metrics::Key;
+use regex::Regex;
+
+use super::histogram::{MAX_SAMPLES, SAMPLE_INTERVAL_MS};
+use crate::glob_match;
+
+/// Labels of a series, sorted by label name.
+pub type Labels = Vec<(String, String)>;
+
+/// Returns the sorted labels of a metric key.
+pub fn key_labels(key: &Key) -> Labels {
+    let mut labels: Labels = key
+        .labels()
+        .map(|l| (l.key().to_string(), l.value().to_string()))
+        .collect();
+    labels.sort();
+    labels
+}
+
+/// Renders a series as `name{label1="value1",label2="value2"}`, or only `name` without labels.
+pub fn series_key(name: &str, labels: &Labels) -> String {
+    if labels.is_empty() {
+        return name.to_string();
+    }
+    let labels = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{name}{{{labels}}}")
+}
+
+/// Returns the metric name part of a series key, or of a key like `rate(name)`.
+/// For a function of several metrics like `ratio(a, b)`, the first metric is returned.
+pub fn series_name(series: &str) -> &str {
+    // First series argument of a function like `rate(name)` or `quantile(0.99, name)`
+    let series = match parse_call(series) {
+        Some((_, args)) => args
+            .into_iter()
+            .find(|arg| arg.parse::<f64>().is_err())
+            .unwrap_or_default(),
+        None => series,
+    };
+    series.split('{').next().unwrap_or(series).trim()
+}
+
+/// Returns the names of all metrics of a key, like `a` and `b` of `ratio(a, b)`.
+pub fn key_names(key: &str) -> Vec<String> {
+    match ValueKey::parse(key) {
+        Some(key) => key.filters().iter().map(|f| f.name.clone()).collect(),
+        None => vec![series_name(key).to_string()],
+    }
+}
+
+/// Splits a function call like `quantile(0.99, name{a="1",b="2"})` into its name and
+/// arguments, ignoring the commas inside label matchers.
+fn parse_call(input: &str) -> Option<(&str, Vec<&str>)> {
+    let input = input.trim();
+    let (function, rest) = input.split_once('(')?;
+    if function.is_empty() || !function.chars().all(|c| c.is_ascii_lowercase() || c == '_') {
+        return None;
+    }
+    let body = rest.strip_suffix(')')?;
+
+    let mut args = vec![];
+    let (mut start, mut depth, mut quoted, mut escaped) = (0, 0, false, false);
+    for (i, c) in body.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '{' if !quoted => depth += 1,
+            '}' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                args.push(body[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(body[start..].trim());
+    Some((function, args))
+}
+
+/// Operator of a [`LabelMatcher`], like in Prometheus selectors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchOp {
+    /// `label="value"`, a value containing `*` is a glob pattern.
+    Equal,
+    /// `label!="value"`, a value containing `*` is a glob pattern.
+    NotEqual,
+    /// `label=~"regex"`, matching the whole value.
+    Regex,
+    /// `label!~"regex"`, matching the whole value.
+    NotRegex,
+}
+
+/// Matches the value of a label, a missing label having an empty value.
+#[derive(Debug, Clone)]
+pub struct LabelMatcher {
+    pub label: String,
+    pub op: MatchOp,
+    pub value: String,
+    regex: Option<Regex>,
+}
+
+impl PartialEq for LabelMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.label == other.label && self.op == other.op && self.value == other.value
+    }
+}
+
+impl LabelMatcher {
+    /// Returns `None` if the value of a regex operator is not a valid regex.
+    pub fn new(label: &str, op: MatchOp, value: &str) -> Option<Self> {
+        let regex = match op {
+            MatchOp::Regex | MatchOp::NotRegex => Some(Regex::new(&format!("^(?:{value})$")).ok()?),
+            MatchOp::Equal | MatchOp::NotEqual => None,
+        };
+        Some(Self {
+            label: label.to_string(),
+            op,
+            value: value.to_string(),
+            regex,
+        })
+    }
+
+    /// Whether the value is a glob pattern of an equality operator.
+    pub fn is_glob(&self) -> bool {
+        matches!(self.op, MatchOp::Equal | MatchOp::NotEqual) && self.value.contains('*')
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        let equal = match self.is_glob() {
+            true => glob_match(&self.value, value),
+            false => self.value == value,
+        };
+        match (self.op, &self.regex) {
+            (MatchOp::Equal, _) => equal,
+            (MatchOp::NotEqual, _) => !equal,
+            (MatchOp::Regex, Some(regex)) => regex.is_match(value),
+            (MatchOp::NotRegex, Some(regex)) => !regex.is_match(value),
+            (_, None) => false,
+        }
+    }
+}
+
+/// Selects the series of a metric by name and label matchers, parsed from a string like
+/// `name{label1="value1",label2=~"5.."}`. The selected series are the ones matching all matchers.
+///
+/// A filter with only exact `=` matchers selects a fixed set of series. Otherwise it is a
+/// selector, whose series are resolved again whenever values are read, so new label values
+/// are picked up as they appear.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeriesFilter {
+    pub name: String,
+    /// Matchers sorted by label name.
+    pub matchers: Vec<LabelMatcher>,
+}
+
+impl SeriesFilter {
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let Some(start) = input.find('{') else {
+            return Some(Self {
+                name: input.to_string(),
+                matchers: vec![],
+            });
+        };
+        let name = input[..start].trim().to_string();
+        let body = input[start + 1..].strip_suffix('}')?;
+
+        let mut matchers = vec![];
+        let mut chars = body.chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+            let mut label = String::new();
+            while let Some(c) = chars.next_if(|c| *c != '=' && *c != '!') {
+                label.push(c);
+            }
+            let op = match (chars.next()?, chars.next_if(|c| *c == '=' || *c == '~')) {
+                ('=', None) => MatchOp::Equal,
+                ('=', Some('~')) => MatchOp::Regex,
+                ('!', Some('=')) => MatchOp::NotEqual,
+                ('!', Some('~')) => MatchOp::NotRegex,
+                _ => return None,
+            };
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.next()? != '"' {
+                return None;
+            }
+            let mut value = String::new();
+            loop {
+                match chars.next()? {
+                    '\\' => value.push(chars.next()?),
+                    '"' => break,
+                    c => value.push(c),
+                }
+            }
+            matchers.push(LabelMatcher::new(label.trim(), op, &value)?);
+        }
+        matchers.sort_by(|a, b| a.label.cmp(&b.label));
+        Some(Self { name, matchers })
+    }
+
+    pub fn matches(&self, labels: &Labels) -> bool {
+        self.matchers.iter().all(|m| {
+            let value = labels
+                .iter()
+                .find(|(label, _)| *label == m.label)
+                .map(|(_, value)| value.as_str());
+            m.matches(value.unwrap_or_default())
+        })
+    }
+
+    /// Whether the filter has other matchers than exact `=` ones.
+    pub fn is_selector(&self) -> bool {
+        self.matchers
+            .iter()
+            .any(|m| m.op != MatchOp::Equal || m.is_glob())
+    }
+}
+
+/// A key requested from `metrics_value`: a series filter, optionally wrapped in a function
+/// like `quantile(0.99, name{label="value"})` or `rate(name)`.
+/// Functions on the values of histograms take their values count.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueKey {
+    Series(SeriesFilter),
+    /// Quantile of a histogram over the last minutes.
+    Quantile(f64, SeriesFilter),
+    /// `rate(name)`, change per second over the last sampling interval.
+    Rate(SeriesFilter),
+    /// `ratio(a, b)`, value of the first filter divided by the value of the second one.
+    /// Counters and histograms are compared by their rate over the last sampling interval.
+    Ratio(SeriesFilter, SeriesFilter),
+    /// `percent(name{label="value"})`, share in percent of the selected series in the sum of
+    /// all series of the metric, by rate for counters and histograms.
+    Percent(SeriesFilter),
+    /// `moving_avg(name, 60)`, average value over the last seconds, at most the length of
+    /// the history.
+    MovingAvg(SeriesFilter, u64),
+    /// `diff(name)`, change since the previous sample.
+    Diff(SeriesFilter),
+}
+
+impl ValueKey {
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let Some((function, args)) = parse_call(input) else {
+            return SeriesFilter::parse(input).map(Self::Series);
+        };
+        let series = |i: usize| SeriesFilter::parse(args[i]);
+        match (function, args.len()) {
+            ("quantile", 2) => {
+                let q: f64 = args[0].parse().ok()?;
+                if !(0.0..=1.0).contains(&q) {
+                    return None;
+                }
+                Some(Self::Quantile(q, series(1)?))
+            }
+            ("rate", 1) => Some(Self::Rate(series(0)?)),
+            ("ratio", 2) => Some(Self::Ratio(series(0)?, series(1)?)),
+            ("percent", 1) => Some(Self::Percent(series(0)?)),
+            ("moving_avg", 2) => {
+                let window: u64 = args[1].trim_end_matches('s').parse().ok()?;
+                if window == 0 {
+                    return None;
+                }
+                // Longer windows average the whole history anyway
+                let max_window = MAX_SAMPLES as u64 * SAMPLE_INTERVAL_MS / 1000;
+                Some(Self::MovingAvg(series(0)?, window.min(max_window)))
+            }
+            ("diff", 1) => Some(Self::Diff(series(0)?)),
+            _ => None,
+        }
+    }
+
+    /// The filters selecting the series of the value.
+    pub fn filters(&self) -> Vec<&SeriesFilter> {
+        match self {
+            ValueKey::Series(filter)
+            | ValueKey::Quantile(_, filter)
+            | ValueKey::Rate(filter)
+            | ValueKey::Percent(filter)
+            | ValueKey::MovingAvg(filter, _)
+            | ValueKey::Diff(filter) => vec![filter],
+            ValueKey::Ratio(a, b) => vec![a, b],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> Labels {
+        pairs
+            .iter()
+            .map(|(l, v)| (l.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn matcher(input: &str) -> LabelMatcher {
+        let mut filter = SeriesFilter::parse(input).expect("valid filter");
+        assert_eq!(filter.matchers.len(), 1);
+        filter.matchers.remove(0)
+    }
+
+    #[test]
+    fn parses_each_operator() {
+        let filter = SeriesFilter::parse(
+            r#"requests{status="200", method!="GET", code=~"5..", path!~"/a.*"}"#,
+        )
+        .expect("valid filter");
+        assert_eq!(filter.name, "requests");
+        let ops: Vec<(&str, MatchOp, &str)> = filter
+            .matchers
+            .iter()
+            .map(|m| (m.label.as_str(), m.op, m.value.as_str()))
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                ("code", MatchOp::Regex, "5.."),
+                ("method", MatchOp::NotEqual, "GET"),
+                ("path", MatchOp::NotRegex, "/a.*"),
+                ("status", MatchOp::Equal, "200"),
+            ]
+        );
+        assert!(filter.is_selector());
+
+        let series = labels(&[
+            ("status", "200"),
+            ("method", "POST"),
+            ("code", "503"),
+            ("path", "/b"),
+        ]);
+        assert!(filter.matches(&series));
+        assert!(!filter.matches(&labels(&[
+            ("status", "200"),
+            ("method", "GET"),
+            ("code", "503"),
+            ("path", "/b"),
+        ])));
+    }
+
+    #[test]
+    fn operators_match_values() {
+        assert!(matcher(r#"m{a="x"}"#).matches("x"));
+        assert!(!matcher(r#"m{a="x"}"#).matches("y"));
+        assert!(matcher(r#"m{a!="x"}"#).matches("y"));
+        assert!(!matcher(r#"m{a!="x"}"#).matches("x"));
+        // Regexes match the whole value
+        assert!(matcher(r#"m{a=~"5.."}"#).matches("503"));
+        assert!(!matcher(r#"m{a=~"5.."}"#).matches("1503"));
+        assert!(matcher(r#"m{a!~"5.."}"#).matches("200"));
+        assert!(!matcher(r#"m{a!~"5.."}"#).matches("500"));
+        // A missing label has an empty value
+        assert!(SeriesFilter::parse(r#"m{a=""}"#)
+            .expect("valid filter")
+            .matches(&labels(&[("b", "x")])));
+    }
+
+    #[test]
+    fn glob_values() {
+        let trailing = matcher(r#"m{path="/api/*"}"#);
+        assert!(trailing.is_glob());
+        assert!(trailing.matches("/api/users"));
+        assert!(trailing.matches("/api/"));
+        assert!(!trailing.matches("/health"));
+
+        let leading = matcher(r#"m{host="*.example.com"}"#);
+        assert!(leading.matches("a.example.com"));
+        assert!(!leading.matches("example.org"));
+
+        let not = matcher(r#"m{path!="/api/*"}"#);
+        assert!(not.is_glob());
+        assert!(not.matches("/health"));
+        assert!(!not.matches("/api/users"));
+
+        // Only equality operators take globs
+        assert!(!matcher(r#"m{a=~"x*"}"#).is_glob());
+        assert!(SeriesFilter::parse(r#"m{a="*"}"#)
+            .expect("valid filter")
+            .is_selector());
+    }
+
+    #[test]
+    fn invalid_regex_is_an_error() {
+        assert_eq!(SeriesFilter::parse(r#"m{a=~"("}"#), None);
+        assert_eq!(SeriesFilter::parse(r#"m{a!~"[z-a]"}"#), None);
+        assert_eq!(ValueKey::parse(r#"rate(m{a=~"("})"#), None);
+    }
+
+    #[test]
+    fn escaped_quotes() {
+        let m = matcher(r#"m{msg="say \"hi\", \\o/"}"#);
+        assert_eq!(m.value, r#"say "hi", \o/"#);
+        assert!(m.matches(r#"say "hi", \o/"#));
+        // Unterminated values are invalid
+        assert_eq!(SeriesFilter::parse(r#"m{msg="x\"}"#), None);
+    }
+
+    #[test]
+    fn empty_selector() {
+        let filter = SeriesFilter::parse("requests{}").expect("valid filter");
+        assert_eq!(filter.name, "requests");
+        assert!(filter.matchers.is_empty());
+        assert!(!filter.is_selector());
+        assert!(filter.matches(&labels(&[("status", "200")])));
+        assert_eq!(SeriesFilter::parse("requests{ , }"), Some(filter));
+        assert_eq!(SeriesFilter::parse("requests{"), None);
+        assert_eq!(SeriesFilter::parse(r#"requests{status}"#), None);
+    }
+}
@@ -0,0 +1,169 @@
+//! Markdown of the [`ChartType::Text`](crate::ChartType::Text) panels, with placeholders like
+//! `Current queue depth: {{queue_depth}}` filled from the current values of the metrics.
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    recorder::{
+        series::{series_name, ValueKey},
+        DashboardRecorder, MetricMeta,
+    },
+    units::{format_number, UnitInfo, DEFAULT_PRECISION},
+};
+
+/// Shown for the placeholders without a value, like the dashboard does.
+const NO_VALUE: &str = "--";
+
+/// A placeholder of a template, spanning `{{` to `}}` in the template.
+struct Placeholder<'a> {
+    range: Range<usize>,
+    key: &'a str,
+}
+
+/// Finds the placeholders of a template. The braces of label matchers and the quoted label
+/// values are part of the key, so `{{requests{status="500"}}}` takes `requests{status="500"}`.
+/// An unclosed `{{` is kept as text.
+fn parse_placeholders(template: &str) -> Vec<Placeholder<'_>> {
+    let mut placeholders = vec![];
+    let mut offset = 0;
+    while let Some(open) = template[offset..].find("{{") {
+        let start = offset + open;
+        let Some(len) = key_len(&template[start + 2..]) else {
+            break;
+        };
+        let end = start + 2 + len;
+        placeholders.push(Placeholder {
+            range: start..end + 2,
+            key: template[start + 2..end].trim(),
+        });
+        offset = end + 2;
+    }
+    placeholders
+}
+
+/// Length of the key before the closing `}}`, like the arguments split by `parse_call`.
+fn key_len(input: &str) -> Option<usize> {
+    let (mut depth, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '{' if !quoted => depth += 1,
+            '}' if !quoted && depth > 0 => depth -= 1,
+            '}' if !quoted && input[i..].starts_with("}}") => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Keys of the placeholders of a template, like `queue_depth` of `{{queue_depth}}`.
+pub(crate) fn placeholders(template: &str) -> Vec<&str> {
+    parse_placeholders(template)
+        .into_iter()
+        .map(|p| p.key)
+        .collect()
+}
+
+/// Replaces the placeholders of a template by the current values in the unit of their
+/// metric. A placeholder takes any key of `metrics_value`, like `{{rate(requests)}}`.
+pub(crate) fn render(recorder: &DashboardRecorder, template: &str) -> String {
+    let keys = placeholders(template);
+    let metas: HashMap<String, MetricMeta> = recorder
+        .metrics()
+        .into_iter()
+        .map(|m| (m.key.clone(), m))
+        .collect();
+    let values: HashMap<String, String> = recorder
+        .metrics_value(keys)
+        .into_iter()
+        .filter_map(|v| {
+            let value = v.value_f64.or(v.value_u64.map(|v| v as f64))?;
+            Some((v.key.clone(), format_value(&v.key, value, &metas)))
+        })
+        .collect();
+    fill(template, |key| values.get(key).map(String::as_str))
+}
+
+/// Replaces each placeholder by its value, or by [`NO_VALUE`] when it has none.
+fn fill<'a>(template: &str, value: impl Fn(&str) -> Option<&'a str>) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut copied = 0;
+    for placeholder in parse_placeholders(template) {
+        text.push_str(&template[copied..placeholder.range.start]);
+        text.push_str(value(placeholder.key).unwrap_or(NO_VALUE));
+        copied = placeholder.range.end;
+    }
+    text.push_str(&template[copied..]);
+    text
+}
+
+/// Formats the value of a key in the unit and precision of its metric, per second for rates
+/// and in percent for shares. Ratios have no unit.
+fn format_value(key: &str, value: f64, metas: &HashMap<String, MetricMeta>) -> String {
+    let meta = metas.get(series_name(key));
+    let precision = meta.and_then(|m| m.precision).unwrap_or(DEFAULT_PRECISION);
+    let unit = meta
+        .and_then(|m| m.unit.as_deref())
+        .and_then(UnitInfo::from_label)
+        .map(|unit| UnitInfo { precision, ..unit });
+    match (ValueKey::parse(key), unit) {
+        (Some(ValueKey::Ratio(..)), _) => format_number(value, DEFAULT_PRECISION),
+        (Some(ValueKey::Percent(_)), _) => {
+            format!("{} %", format_number(value, DEFAULT_PRECISION))
+        }
+        (_, None) => format_number(value, precision),
+        (Some(ValueKey::Rate(_)), Some(unit)) => format!("{}/s", unit.format(value)),
+        (_, Some(unit)) => unit.format(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill_keys(template: &str) -> String {
+        fill(template, |_| Some("1"))
+    }
+
+    #[test]
+    fn plain_key() {
+        assert_eq!(
+            placeholders("Depth: {{ queue_depth }}"),
+            vec!["queue_depth"]
+        );
+        assert_eq!(fill_keys("Depth: {{queue_depth}} items"), "Depth: 1 items");
+    }
+
+    #[test]
+    fn labeled_key() {
+        let template = r#"Errors: {{http_requests_total{status="500"}}}!"#;
+        assert_eq!(
+            placeholders(template),
+            vec![r#"http_requests_total{status="500"}"#]
+        );
+        assert_eq!(fill_keys(template), "Errors: 1!");
+        // Braces inside quoted values are part of the value
+        assert_eq!(
+            placeholders(r#"{{a{path="/}}"}}} {{b}}"#),
+            vec![r#"a{path="/}}"}"#, "b"]
+        );
+    }
+
+    #[test]
+    fn function_key() {
+        let template = r#"{{ratio(errors{code=~"5.."}, requests)}} and {{rate(requests)}}"#;
+        assert_eq!(
+            placeholders(template),
+            vec![r#"ratio(errors{code=~"5.."}, requests)"#, "rate(requests)"]
+        );
+        assert_eq!(fill_keys(template), "1 and 1");
+    }
+
+    #[test]
+    fn unclosed_placeholder() {
+        assert_eq!(placeholders("{{a}} then {{b"), vec!["a"]);
+        assert_eq!(fill_keys("{{a}} then {{b"), "1 then {{b");
+        assert_eq!(fill("{{missing}}", |_| None), NO_VALUE);
+    }
+}
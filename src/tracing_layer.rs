@@ -0,0 +1,222 @@
+//! [`tracing_subscriber::Layer`] which turns spans and events into metrics:
+//!
+//! ```rust,ignore
+//! use tracing_subscriber::prelude::*;
+//!
+//! tracing_subscriber::registry()
+//!     .with(MetricsLayer::new().with_field_label("http.method", "method"))
+//!     .init();
+//! ```
+//!
+//! For each span, the layer records the time between its creation and its close into a
+//! `{base}_duration_seconds` histogram and counts each entry into a `{base}_entries_total`
+//! counter, where `{base}` is rendered from the name template. With
+//! [`MetricsLayer::with_event_counts`], events are also counted by level and target into
+//! `log_events_total`, like [`LogMetricsLayer`](crate::log_metrics::LogMetricsLayer) does.
+use std::{collections::HashSet, fmt::Debug, time::Instant};
+
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use parking_lot::Mutex;
+use tracing_core::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::log_metrics::{record_log_event, register_log_events};
+
+const DEFAULT_NAME_TEMPLATE: &str = "span_{name}";
+
+/// Layer which records span durations and entries, and optionally counts events by level
+/// and target.
+pub struct MetricsLayer {
+    name_template: String,
+    field_labels: Vec<(String, String)>,
+    event_counts: bool,
+    described: Mutex<HashSet<String>>,
+}
+
+impl MetricsLayer {
+    /// Creates a layer with the default `span_{name}` template and without span field labels.
+    pub fn new() -> Self {
+        Self {
+            name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+            field_labels: vec![],
+            event_counts: false,
+            described: Default::default(),
+        }
+    }
+
+    /// Sets the template of the span metrics base name.
+    /// `{name}` and `{target}` are replaced with the span name and target,
+    /// characters which are not allowed in metric names are replaced with `_`.
+    pub fn with_name_template(mut self, template: &str) -> Self {
+        self.name_template = template.to_string();
+        self
+    }
+
+    /// Records the span field `field` as the label `label` on the span metrics.
+    pub fn with_field_label(mut self, field: &str, label: &str) -> Self {
+        self.field_labels
+            .push((field.to_string(), label.to_string()));
+        self
+    }
+
+    /// Counts the events into `log_events_total` and registers the "Log events" chart.
+    /// Disabled by default, as the events are usually counted by a
+    /// [`LogMetricsLayer`](crate::log_metrics::LogMetricsLayer), or by a `LogMetrics` logger
+    /// whose records are forwarded into `tracing`, which would count them twice.
+    pub fn with_event_counts(mut self, enabled: bool) -> Self {
+        if enabled {
+            register_log_events();
+        }
+        self.event_counts = enabled;
+        self
+    }
+
+    fn base_name(&self, meta: &Metadata<'_>) -> String {
+        let name = self
+            .name_template
+            .replace("{name}", meta.name())
+            .replace("{target}", meta.target());
+        sanitize_metric_name(&name)
+    }
+
+    fn describe(&self, base: &str, name: &str) {
+        let mut described = self.described.lock();
+        if described.contains(base) {
+            return;
+        }
+        describe_histogram!(
+            format!("{base}_duration_seconds"),
+            Unit::Seconds,
+            format!("Duration of span {name}")
+        );
+        describe_counter!(
+            format!("{base}_entries_total"),
+            Unit::Count,
+            format!("Entries of span {name}")
+        );
+        described.insert(base.to_string());
+    }
+}
+
+impl Default for MetricsLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Per span state, stored in the span extensions.
+struct SpanMetrics {
+    base: String,
+    labels: Vec<(String, String)>,
+    created_at: Instant,
+}
+
+impl<S> Layer<S> for MetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let base = self.base_name(attrs.metadata());
+        self.describe(&base, attrs.metadata().name());
+
+        let mut labels = vec![];
+        attrs.record(&mut LabelVisitor {
+            field_labels: &self.field_labels,
+            labels: &mut labels,
+        });
+        span.extensions_mut().insert(SpanMetrics {
+            base,
+            labels,
+            created_at: Instant::now(),
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(metrics) = extensions.get_mut::<SpanMetrics>() {
+            values.record(&mut LabelVisitor {
+                field_labels: &self.field_labels,
+                labels: &mut metrics.labels,
+            });
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        if let Some(metrics) = extensions.get::<SpanMetrics>() {
+            counter!(format!("{}_entries_total", metrics.base), &metrics.labels).increment(1);
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        if let Some(metrics) = extensions.get::<SpanMetrics>() {
+            histogram!(
+                format!("{}_duration_seconds", metrics.base),
+                &metrics.labels
+            )
+            .record(metrics.created_at.elapsed().as_secs_f64());
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if !self.event_counts {
+            return;
+        }
+        let meta = event.metadata();
+        record_log_event(meta.level().as_str(), meta.target());
+    }
+}
+
+/// Collects the values of the mapped span fields as labels.
+struct LabelVisitor<'a> {
+    field_labels: &'a [(String, String)],
+    labels: &'a mut Vec<(String, String)>,
+}
+
+impl LabelVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: String) {
+        let Some((_, label)) = self.field_labels.iter().find(|(f, _)| f == field.name()) else {
+            return;
+        };
+        match self.labels.iter_mut().find(|(l, _)| l == label) {
+            Some(slot) => slot.1 = value,
+            None => self.labels.push((label.clone(), value)),
+        }
+    }
+}
+
+impl Visit for LabelVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.insert(field, format!("{value:?}"));
+    }
+}
+
+fn sanitize_metric_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
@@ -0,0 +1,165 @@
+use metrics::Unit;
+use serde::Serialize;
+
+/// Every unit of `metrics::Unit`.
+pub(crate) const UNITS: [Unit; 17] = [
+    Unit::Count,
+    Unit::Percent,
+    Unit::Seconds,
+    Unit::Milliseconds,
+    Unit::Microseconds,
+    Unit::Nanoseconds,
+    Unit::Tebibytes,
+    Unit::Gigibytes,
+    Unit::Mebibytes,
+    Unit::Kibibytes,
+    Unit::Bytes,
+    Unit::TerabitsPerSecond,
+    Unit::GigabitsPerSecond,
+    Unit::MegabitsPerSecond,
+    Unit::KilobitsPerSecond,
+    Unit::BitsPerSecond,
+    Unit::CountPerSecond,
+];
+
+/// Decimal places shown by default.
+pub(crate) const DEFAULT_PRECISION: u8 = 2;
+
+/// How the values of a unit are displayed by the dashboard.
+///
+/// A value is converted to the base unit of its kind, then shown in the largest scale not
+/// greater than the value: `1536` bytes as `1.5 KiB`, `0.002` seconds as `2 ms`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct UnitInfo {
+    /// Canonical label of the unit, like `ms`.
+    pub label: String,
+    /// Label of the base unit of the kind, like `s` for every time unit.
+    pub base: String,
+    /// Value of one unit in the base unit, like `0.001` for milliseconds.
+    pub factor: f64,
+    /// Units the values are displayed in, from the smallest.
+    pub scales: Vec<UnitScale>,
+    /// Decimal places shown.
+    pub precision: u8,
+}
+
+/// A display unit of a [`UnitInfo`].
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct UnitScale {
+    pub label: String,
+    /// Value of one display unit in the base unit.
+    pub factor: f64,
+}
+
+impl UnitInfo {
+    pub fn from_unit(unit: Unit) -> Self {
+        let time = [
+            (Unit::Nanoseconds.as_canonical_label(), 1e-9),
+            (Unit::Microseconds.as_canonical_label(), 1e-6),
+            (Unit::Milliseconds.as_canonical_label(), 1e-3),
+            (Unit::Seconds.as_canonical_label(), 1.0),
+            ("min", 60.0),
+            ("h", 3600.0),
+        ];
+        let bytes = [
+            (Unit::Bytes.as_canonical_label(), 1.0),
+            (Unit::Kibibytes.as_canonical_label(), 1024.0),
+            (Unit::Mebibytes.as_canonical_label(), 1024.0 * 1024.0),
+            (
+                Unit::Gigibytes.as_canonical_label(),
+                1024.0 * 1024.0 * 1024.0,
+            ),
+            (
+                Unit::Tebibytes.as_canonical_label(),
+                1024.0 * 1024.0 * 1024.0 * 1024.0,
+            ),
+        ];
+        let bits = [
+            (Unit::BitsPerSecond.as_canonical_label(), 1.0),
+            (Unit::KilobitsPerSecond.as_canonical_label(), 1e3),
+            (Unit::MegabitsPerSecond.as_canonical_label(), 1e6),
+            (Unit::GigabitsPerSecond.as_canonical_label(), 1e9),
+            (Unit::TerabitsPerSecond.as_canonical_label(), 1e12),
+        ];
+        let label = unit.as_canonical_label();
+        let scales: &[(&str, f64)] = match unit {
+            Unit::Nanoseconds | Unit::Microseconds | Unit::Milliseconds | Unit::Seconds => &time,
+            Unit::Bytes | Unit::Kibibytes | Unit::Mebibytes | Unit::Gigibytes | Unit::Tebibytes => {
+                &bytes
+            }
+            Unit::BitsPerSecond
+            | Unit::KilobitsPerSecond
+            | Unit::MegabitsPerSecond
+            | Unit::GigabitsPerSecond
+            | Unit::TerabitsPerSecond => &bits,
+            Unit::Count | Unit::Percent | Unit::CountPerSecond => &[(label, 1.0)],
+        };
+        let factor = scales
+            .iter()
+            .find(|(l, _)| *l == label)
+            .map_or(1.0, |(_, factor)| *factor);
+        let base = scales
+            .iter()
+            .find(|(_, factor)| *factor == 1.0)
+            .map_or(label, |(base, _)| base);
+        Self {
+            label: label.to_string(),
+            base: base.to_string(),
+            factor,
+            scales: scales
+                .iter()
+                .map(|(label, factor)| UnitScale {
+                    label: label.to_string(),
+                    factor: *factor,
+                })
+                .collect(),
+            precision: DEFAULT_PRECISION,
+        }
+    }
+
+    /// Finds the unit of a label, either canonical like `ms` or named like `Milliseconds`.
+    pub fn from_label(label: &str) -> Option<Self> {
+        unit_from_label(label).map(Self::from_unit)
+    }
+
+    /// Formats a value like the dashboard, in the largest scale not greater than the value.
+    pub fn format(&self, value: f64) -> String {
+        let base = value * self.factor;
+        // Values below the smallest scale, like 0, stay in their own unit
+        let scale = self
+            .scales
+            .iter()
+            .rfind(|s| base.abs() >= s.factor)
+            .or_else(|| self.scales.iter().find(|s| s.label == self.label));
+        match scale {
+            Some(scale) if !scale.label.is_empty() => format!(
+                "{} {}",
+                format_number(base / scale.factor, self.precision),
+                scale.label
+            ),
+            Some(scale) => format_number(base / scale.factor, self.precision),
+            None => format_number(value, self.precision),
+        }
+    }
+}
+
+/// Finds the unit of a label, either canonical like `ms` or named like `Milliseconds`.
+pub(crate) fn unit_from_label(label: &str) -> Option<Unit> {
+    UNITS
+        .iter()
+        .copied()
+        .find(|u| u.as_canonical_label() == label || u.as_str().eq_ignore_ascii_case(label))
+}
+
+/// Formats a number with at most `precision` decimal places, without trailing zeros.
+pub(crate) fn format_number(value: f64, precision: u8) -> String {
+    let text = format!("{value:.*}", precision as usize);
+    let text = match text.contains('.') {
+        true => text.trim_end_matches('0').trim_end_matches('.'),
+        false => &text,
+    };
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
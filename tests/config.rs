@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use metrics_dashboard::{ChartType, ConfigError, DashboardOptions, StatValue};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// Checks the options of the `dashboard.*` fixtures, which are the same in each format.
+fn check_options(options: DashboardOptions) {
+    assert!(options.include_default);
+    assert!(options.editable);
+
+    let [ChartType::Line { metrics, desc, .. }] = options.custom_charts.as_slice() else {
+        panic!("expected one line chart, got {:?}", options.custom_charts);
+    };
+    assert_eq!(metrics, &["http_requests_total"]);
+    assert_eq!(desc, "Http requests");
+
+    let [section] = options.sections.as_slice() else {
+        panic!("expected one section, got {:?}", options.sections);
+    };
+    assert_eq!(section.title, "Queue");
+    assert!(section.collapsed);
+    let [panel] = section.charts.as_slice() else {
+        panic!("expected one chart, got {:?}", section.charts);
+    };
+    assert_eq!(panel.width, Some(3));
+    let ChartType::Stat {
+        metric,
+        unit,
+        value: StatValue::Quantile(q),
+        ..
+    } = &panel.chart
+    else {
+        panic!("expected a quantile stat, got {:?}", panel.chart);
+    };
+    assert_eq!(metric, "queue_wait_seconds");
+    assert_eq!(unit, "seconds");
+    assert_eq!(*q, 0.99);
+}
+
+#[test]
+fn loads_yaml() {
+    check_options(DashboardOptions::from_file(fixture("dashboard.yaml")).expect("valid options"));
+}
+
+#[test]
+fn loads_json() {
+    check_options(DashboardOptions::from_file(fixture("dashboard.json")).expect("valid options"));
+}
+
+#[test]
+fn loads_toml() {
+    check_options(DashboardOptions::from_file(fixture("dashboard.toml")).expect("valid options"));
+}
+
+#[test]
+fn reports_the_path_of_an_invalid_field() {
+    let err = DashboardOptions::from_file(fixture("invalid_field.yaml")).expect_err("invalid");
+    let ConfigError::Parse { path, message } = &err else {
+        panic!("expected a parse error, got {err:?}");
+    };
+    assert_eq!(path, "custom_charts[1].meta.value");
+    assert!(message.contains("invalid type"), "{message}");
+    assert!(err.to_string().starts_with(path.as_str()));
+}
+
+#[test]
+fn rejects_unsupported_formats() {
+    let err = DashboardOptions::from_file(fixture("../config.rs")).expect_err("unsupported");
+    assert!(matches!(err, ConfigError::UnsupportedFormat(ext) if ext == "rs"));
+}
+
+#[test]
+fn reports_the_location_of_an_invalid_chart() {
+    let options = DashboardOptions {
+        custom_charts: vec![
+            ChartType::line(vec!["requests".to_string()], "Requests", ""),
+            ChartType::line(vec![], "Empty", ""),
+        ],
+        ..Default::default()
+    };
+    let err = options.validate().expect_err("invalid chart");
+    let ConfigError::InvalidChart { chart, .. } = &err else {
+        panic!("expected an invalid chart, got {err:?}");
+    };
+    assert_eq!(chart, "custom_charts[1]");
+}
@@ -0,0 +1,141 @@
+use std::{
+    convert::Infallible,
+    future::{pending, Future, Pending},
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use http::{Request, Response};
+use metrics_dashboard::{
+    grpc::{GrpcMetricLayer, GRPC_REQUESTS_DURATION_SECONDS, GRPC_REQUESTS_TOTAL},
+    recorder::DashboardRecorder,
+    DashboardOptions,
+};
+use tokio::net::TcpListener;
+use tonic::{
+    transport::{server::TcpIncoming, Channel, Server},
+    Code,
+};
+use tonic_health::pb::{health_client::HealthClient, HealthCheckRequest};
+use tower_layer::Layer;
+use tower_service::Service;
+
+const CHECK_METHOD: &str = "grpc.health.v1.Health/Check";
+
+#[tokio::test]
+async fn records_grpc_requests() {
+    let recorder = DashboardRecorder::new(DashboardOptions::default());
+    metrics::set_global_recorder(recorder.clone()).expect("Should register the recorder");
+
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter
+        .set_service_status("demo", tonic_health::ServingStatus::Serving)
+        .await;
+
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Should bind a port");
+    let addr = listener.local_addr().expect("Should have an address");
+    let incoming = TcpIncoming::from_listener(listener, true, None).expect("Should accept");
+    tokio::spawn(
+        Server::builder()
+            .layer(GrpcMetricLayer::new())
+            .add_service(health_service)
+            .serve_with_incoming(incoming),
+    );
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .expect("Should parse the address")
+        .connect()
+        .await
+        .expect("Should connect to the server");
+    let mut client = HealthClient::new(channel);
+    for _ in 0..2 {
+        client
+            .check(HealthCheckRequest {
+                service: "demo".to_string(),
+            })
+            .await
+            .expect("Should check a known service");
+    }
+    let status = client
+        .check(HealthCheckRequest {
+            service: "unknown".to_string(),
+        })
+        .await
+        .expect_err("Should not find an unknown service");
+    assert_eq!(status.code(), Code::NotFound);
+
+    let ok = format!("{GRPC_REQUESTS_TOTAL}{{method=\"{CHECK_METHOD}\", status=\"OK\"}}");
+    let not_found =
+        format!("{GRPC_REQUESTS_TOTAL}{{method=\"{CHECK_METHOD}\", status=\"NOT_FOUND\"}}");
+    let duration = format!("{GRPC_REQUESTS_DURATION_SECONDS}{{method=\"{CHECK_METHOD}\"}}");
+    let values = recorder.metrics_value(vec![&ok, &not_found, &duration]);
+    let counts: Vec<_> = values
+        .iter()
+        .map(|v| (v.key.as_str(), v.value_u64))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![
+            (ok.as_str(), Some(2)),
+            (not_found.as_str(), Some(1)),
+            (duration.as_str(), Some(3)),
+        ]
+    );
+
+    let histogram = recorder
+        .histogram_value(&duration)
+        .expect("Should record the duration as a histogram");
+    let recorded: u64 = histogram
+        .samples
+        .iter()
+        .flat_map(|sample| sample.counts.iter())
+        .sum();
+    assert_eq!(recorded, 3);
+}
+
+/// A service whose calls never respond.
+struct Stalled;
+
+impl Service<Request<()>> for Stalled {
+    type Response = Response<()>;
+    type Error = Infallible;
+    type Future = Pending<Result<Response<()>, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: Request<()>) -> Self::Future {
+        pending()
+    }
+}
+
+#[test]
+fn records_cancelled_calls() {
+    let recorder = DashboardRecorder::new(DashboardOptions::default());
+    metrics::with_local_recorder(&recorder, || {
+        let mut service = GrpcMetricLayer::new().layer(Stalled);
+        for path in ["/grpc.health.v1.Health/Watch", "/not/a/grpc/method"] {
+            let request = Request::builder().uri(path).body(()).expect("Should build");
+            let mut call = pin!(service.call(request));
+            let mut cx = Context::from_waker(Waker::noop());
+            assert!(call.as_mut().poll(&mut cx).is_pending());
+        }
+    });
+
+    let watch = format!(
+        "{GRPC_REQUESTS_TOTAL}{{method=\"grpc.health.v1.Health/Watch\", status=\"CANCELLED\"}}"
+    );
+    let unknown = format!("{GRPC_REQUESTS_TOTAL}{{method=\"unknown\", status=\"CANCELLED\"}}");
+    let values = recorder.metrics_value(vec![&watch, &unknown]);
+    let counts: Vec<_> = values
+        .iter()
+        .map(|v| (v.key.as_str(), v.value_u64))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![(watch.as_str(), Some(1)), (unknown.as_str(), Some(1))]
+    );
+}